@@ -14,6 +14,14 @@ pub enum Action {
     Save,                  //ctrl+S
     Copy,                  //Ctrl+C
     Undo,                  //Ctrl+Z
+    RepeatLastCapture,     //Ctrl+R
+    CaptureRegion(u32),    // the id of a saved region
+    ScrollCapture,         //Ctrl+shift+Enter
+    Record,                //Ctrl+shift+R
+    Watch,                 //Ctrl+shift+W
+    QuickSave,             //Ctrl+shift+S
+    OpenImage,             //Ctrl+shift+O
+    Paste,                 //Ctrl+V
 }
 
 impl Action {
@@ -35,6 +43,14 @@ impl Action {
             Action::Save => String::from("Save"),
             Action::Copy => String::from("Copy"),
             Action::Undo => String::from("Undo modify"),
+            Action::RepeatLastCapture => String::from("Repeat last capture"),
+            Action::CaptureRegion(i) => format!("Capture region {}", i),
+            Action::ScrollCapture => String::from("Scrolling capture"),
+            Action::Record => String::from("Start/stop recording"),
+            Action::Watch => String::from("Start/stop watching for changes"),
+            Action::QuickSave => String::from("Quick save"),
+            Action::OpenImage => String::from("Open image"),
+            Action::Paste => String::from("Paste image"),
         }
     }
     pub fn wants_image_viewer(self) -> bool {
@@ -53,6 +69,14 @@ impl Action {
             Action::Save => true,
             Action::Copy => true,
             Action::Undo => true,
+            Action::RepeatLastCapture => false,
+            Action::CaptureRegion(_) => false,
+            Action::ScrollCapture => false,
+            Action::Record => false,
+            Action::Watch => false,
+            Action::QuickSave => true,
+            Action::OpenImage => false,
+            Action::Paste => false,
         }
     }
 }
@@ -78,6 +102,13 @@ impl AllActionArr {
                 Action::Save,
                 Action::Copy,
                 Action::Undo,
+                Action::RepeatLastCapture,
+                Action::ScrollCapture,
+                Action::Record,
+                Action::Watch,
+                Action::QuickSave,
+                Action::OpenImage,
+                Action::Paste,
             ],
         }
     }
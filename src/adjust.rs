@@ -0,0 +1,240 @@
+pub mod adjust {
+    use crate::error::error::AppError;
+    use image::imageops::{self, FilterType};
+    use image::{Rgba, RgbaImage};
+    use serde::{Deserialize, Serialize};
+
+    // operations on the pixels of the capture, applied in order
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Adjustment {
+        Resize(u32), // maximum width, smaller images are left as they are
+        Rotate(u32), // clockwise, 90, 180 or 270 degrees
+        FlipHorizontal,
+        FlipVertical,
+        Border(u32, [u8; 4]),
+        Shadow(u32),
+        RoundCorners(u32),
+    }
+
+    // the values last used in the editor
+    #[derive(Clone, Copy, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct AdjustSettings {
+        pub max_width: u32,
+        pub border: u32,
+        pub border_color: [u8; 4], // rgba, not premultiplied
+        pub shadow: u32,
+        pub radius: u32,
+    }
+
+    impl Default for AdjustSettings {
+        fn default() -> Self {
+            AdjustSettings {
+                max_width: 1280,
+                border: 10,
+                border_color: [255, 255, 255, 255],
+                shadow: 20,
+                radius: 12,
+            }
+        }
+    }
+
+    impl Adjustment {
+        // the value of a command line flag, like --border 10:#ffffff
+        pub fn parse(flag: &str, value: &str) -> Result<Adjustment, String> {
+            let number = |v: &str| {
+                v.parse::<u32>()
+                    .map_err(|_| format!("{}: '{}' is not a number", flag, v))
+            };
+            match flag {
+                "--resize" => number(value).map(Adjustment::Resize),
+                "--rotate" => match number(value)? {
+                    degrees @ (90 | 180 | 270) => Ok(Adjustment::Rotate(degrees)),
+                    _ => Err(format!("{}: the angle must be 90, 180 or 270", flag)),
+                },
+                "--flip" => match value {
+                    "h" | "horizontal" => Ok(Adjustment::FlipHorizontal),
+                    "v" | "vertical" => Ok(Adjustment::FlipVertical),
+                    _ => Err(format!("{}: use horizontal or vertical", flag)),
+                },
+                "--border" => {
+                    let (width, color) = match value.split_once(':') {
+                        Some((width, color)) => (width, parse_color(color)?),
+                        None => (value, [255, 255, 255, 255]),
+                    };
+                    Ok(Adjustment::Border(number(width)?, color))
+                }
+                "--shadow" => number(value).map(Adjustment::Shadow),
+                "--round" => number(value).map(Adjustment::RoundCorners),
+                _ => Err(format!("unknown option {}", flag)),
+            }
+        }
+
+        pub fn adjust(self, image: RgbaImage) -> RgbaImage {
+            match self {
+                Adjustment::Resize(max_width) => resize(image, max_width),
+                Adjustment::Rotate(90) => imageops::rotate90(&image),
+                Adjustment::Rotate(180) => imageops::rotate180(&image),
+                Adjustment::Rotate(270) => imageops::rotate270(&image),
+                Adjustment::Rotate(_) => image,
+                Adjustment::FlipHorizontal => imageops::flip_horizontal(&image),
+                Adjustment::FlipVertical => imageops::flip_vertical(&image),
+                Adjustment::Border(width, color) => border(&image, width, color),
+                Adjustment::Shadow(size) => shadow(&image, size),
+                Adjustment::RoundCorners(radius) => round_corners(image, radius),
+            }
+        }
+    }
+
+    // buffer is an encoded image, the result is a png
+    pub fn apply(buffer: &[u8], adjustments: &[Adjustment]) -> Result<Vec<u8>, AppError> {
+        let mut image = image::load_from_memory(buffer)
+            .map_err(|e| AppError::Image(e.to_string()))?
+            .to_rgba8();
+        for adjustment in adjustments {
+            image = adjustment.adjust(image);
+        }
+        encode(image)
+    }
+
+    pub fn encode(image: RgbaImage) -> Result<Vec<u8>, AppError> {
+        let mut buffer = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(
+                &mut std::io::Cursor::new(&mut buffer),
+                image::ImageOutputFormat::Png,
+            )
+            .map_err(|e| AppError::Image(e.to_string()))?;
+        Ok(buffer)
+    }
+
+    // jpeg has no alpha channel, the transparent parts become white
+    pub fn encode_jpeg(image: &RgbaImage) -> Result<Vec<u8>, AppError> {
+        let mut white = RgbaImage::from_pixel(image.width(), image.height(), Rgba([255; 4]));
+        imageops::overlay(&mut white, image, 0, 0);
+        let mut buffer = Vec::new();
+        image::DynamicImage::ImageRgba8(white)
+            .to_rgb8()
+            .write_to(
+                &mut std::io::Cursor::new(&mut buffer),
+                image::ImageOutputFormat::Jpeg(90),
+            )
+            .map_err(|e| AppError::Image(e.to_string()))?;
+        Ok(buffer)
+    }
+
+    // #rrggbb or #rrggbbaa
+    pub fn parse_color(text: &str) -> Result<[u8; 4], String> {
+        let hex = text.trim_start_matches('#');
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+                .ok_or(format!("'{}' is not a colour like #rrggbb", text))
+        };
+        match hex.len() {
+            6 => Ok([channel(0)?, channel(2)?, channel(4)?, 255]),
+            8 => Ok([channel(0)?, channel(2)?, channel(4)?, channel(6)?]),
+            _ => Err(format!("'{}' is not a colour like #rrggbb", text)),
+        }
+    }
+
+    pub fn resize(image: RgbaImage, max_width: u32) -> RgbaImage {
+        if max_width == 0 || image.width() <= max_width {
+            return image;
+        }
+        let height = (image.height() as u64 * max_width as u64 / image.width() as u64).max(1);
+        // lanczos keeps the text of the capture sharp when scaling down
+        imageops::resize(&image, max_width, height as u32, FilterType::Lanczos3)
+    }
+
+    pub fn border(image: &RgbaImage, width: u32, color: [u8; 4]) -> RgbaImage {
+        let mut canvas = RgbaImage::from_pixel(
+            image.width() + 2 * width,
+            image.height() + 2 * width,
+            Rgba(color),
+        );
+        imageops::overlay(&mut canvas, image, width as i64, width as i64);
+        canvas
+    }
+
+    // the pixels outside the rounded rectangle become transparent, with antialiased edges
+    pub fn round_corners(mut image: RgbaImage, radius: u32) -> RgbaImage {
+        let (width, height) = image.dimensions();
+        let radius = radius.min(width / 2).min(height / 2);
+        let r = radius as f32;
+        for y in 0..radius.min(height) {
+            for x in 0..radius.min(width) {
+                // distance from the center of the corner circle
+                let dx = r - x as f32 - 0.5;
+                let dy = r - y as f32 - 0.5;
+                let coverage = (r - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+                for (px, py) in [
+                    (x, y),
+                    (width - 1 - x, y),
+                    (x, height - 1 - y),
+                    (width - 1 - x, height - 1 - y),
+                ] {
+                    let pixel = image.get_pixel_mut(px, py);
+                    pixel[3] = (pixel[3] as f32 * coverage).round() as u8;
+                }
+            }
+        }
+        image
+    }
+
+    // a soft shadow below the image, which keeps its shape (rounded corners too)
+    pub fn shadow(image: &RgbaImage, size: u32) -> RgbaImage {
+        if size == 0 {
+            return image.clone();
+        }
+        let offset = size / 4;
+        let pad = size + offset;
+        let (width, height) = (image.width() + 2 * pad, image.height() + 2 * pad);
+        let mut alpha = vec![0.0f32; (width * height) as usize];
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let index = (y + pad + offset) * width + x + pad;
+            alpha[index as usize] = pixel[3] as f32 / 255.0 * 0.5;
+        }
+        // three box blurs are close to a gaussian one
+        let radius = (size / 3).max(1) as usize;
+        for _ in 0..3 {
+            box_blur(&mut alpha, height as usize, width as usize, radius, 1);
+            box_blur(
+                &mut alpha,
+                width as usize,
+                height as usize,
+                radius,
+                width as usize,
+            );
+        }
+        let mut canvas = RgbaImage::from_fn(width, height, |x, y| {
+            let a = alpha[(y * width + x) as usize];
+            Rgba([0, 0, 0, (a * 255.0).round().clamp(0.0, 255.0) as u8])
+        });
+        imageops::overlay(&mut canvas, image, pad as i64, pad as i64);
+        canvas
+    }
+
+    // blurs the rows (stride 1) or the columns (stride = width) of the values
+    fn box_blur(values: &mut [f32], lines: usize, length: usize, radius: usize, stride: usize) {
+        let line_step = if stride == 1 { length } else { 1 };
+        let mut line = vec![0.0f32; length];
+        let window = (2 * radius + 1) as f32;
+        for l in 0..lines {
+            let start = l * line_step;
+            for (i, v) in line.iter_mut().enumerate() {
+                *v = values[start + i * stride];
+            }
+            let mut sum: f32 = line.iter().take(radius + 1).sum();
+            for i in 0..length {
+                values[start + i * stride] = sum / window;
+                if i + radius + 1 < length {
+                    sum += line[i + radius + 1];
+                }
+                if i >= radius {
+                    sum -= line[i - radius];
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+pub mod assets {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    // bundled in the binary, so the app runs from any directory
+    pub const ICON: &[u8] = include_bytes!("../assets/icon.png");
+
+    pub const APP_ID: &str = "io.github.filippoCaste.RustGrabUtility";
+    pub const APP_NAME: &str = "Rust Grab Utility";
+    const SUMMARY: &str = "Take, annotate and save screenshots";
+
+    pub fn icon() -> Option<eframe::IconData> {
+        let icon = image::load_from_memory(ICON).ok()?.to_rgba8();
+        Some(eframe::IconData {
+            width: icon.width(),
+            height: icon.height(),
+            rgba: icon.into_raw(),
+        })
+    }
+
+    fn desktop_entry(exec: &Path) -> String {
+        format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name={}\n\
+             Comment={}\n\
+             Exec=\"{}\"\n\
+             Icon={}\n\
+             Terminal=false\n\
+             Categories=Utility;Graphics;\n\
+             Keywords=screenshot;capture;screen;record;\n",
+            APP_NAME,
+            SUMMARY,
+            exec.display(),
+            APP_ID
+        )
+    }
+
+    fn appstream_metadata() -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <component type=\"desktop-application\">\n  \
+               <id>{id}</id>\n  \
+               <name>{name}</name>\n  \
+               <summary>{summary}</summary>\n  \
+               <metadata_license>CC0-1.0</metadata_license>\n  \
+               <project_license>GPL-3.0-only</project_license>\n  \
+               <description>\n    \
+                 <p>Screenshot utility to capture the whole screen or a selection, \
+                 annotate the image and save it or copy it to the clipboard.</p>\n  \
+               </description>\n  \
+               <launchable type=\"desktop-id\">{id}.desktop</launchable>\n  \
+               <url type=\"homepage\">https://github.com/filippoCaste/rust-grab_utility</url>\n  \
+               <releases>\n    \
+                 <release version=\"{version}\"/>\n  \
+               </releases>\n\
+             </component>\n",
+            id = APP_ID,
+            name = APP_NAME,
+            summary = SUMMARY,
+            version = env!("CARGO_PKG_VERSION")
+        )
+    }
+
+    // writes the desktop entry, the AppStream metadata and the icon under prefix/share
+    pub fn install_desktop_files(prefix: &Path) -> Result<Vec<PathBuf>, String> {
+        let exec = std::env::current_exe().map_err(|e| e.to_string())?;
+        let share = prefix.join("share");
+        let files = [
+            (
+                share.join("applications").join(format!("{}.desktop", APP_ID)),
+                desktop_entry(&exec).into_bytes(),
+            ),
+            (
+                share.join("metainfo").join(format!("{}.metainfo.xml", APP_ID)),
+                appstream_metadata().into_bytes(),
+            ),
+            (
+                share
+                    .join("icons/hicolor/256x256/apps")
+                    .join(format!("{}.png", APP_ID)),
+                ICON.to_vec(),
+            ),
+        ];
+        let mut written = Vec::new();
+        for (path, content) in files {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+            }
+            fs::write(&path, content).map_err(|e| format!("{}: {}", path.display(), e))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    // ~/.local, where the desktop looks for the files of the user
+    pub fn default_prefix() -> Option<PathBuf> {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local"))
+    }
+}
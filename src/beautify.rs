@@ -0,0 +1,229 @@
+pub mod beautify {
+    use crate::adjust::adjust::{encode, round_corners, shadow};
+    use crate::error::error::AppError;
+    use eframe::egui;
+    use image::imageops::{self, FilterType};
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use serde::{Deserialize, Serialize};
+
+    const PREVIEW_WIDTH: u32 = 360;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    pub enum Background {
+        Solid([u8; 4]),
+        Gradient([u8; 4], [u8; 4]), // from the top left corner to the bottom right one
+        Image(String),
+    }
+
+    // the capture on a background, ready for slides and docs
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Framing {
+        pub background: Background,
+        pub padding: u32,
+        pub radius: u32,
+        pub shadow: u32,
+        pub chrome: bool, // a title bar with the three dots above the capture
+    }
+
+    impl Default for Framing {
+        fn default() -> Self {
+            Framing {
+                background: presets()[0].1.clone(),
+                padding: 64,
+                radius: 12,
+                shadow: 24,
+                chrome: false,
+            }
+        }
+    }
+
+    impl Framing {
+        // the same frame for a smaller copy of the capture
+        fn scaled(&self, factor: f32) -> Framing {
+            let scale = |n: u32| (n as f32 * factor).round() as u32;
+            Framing {
+                background: self.background.clone(),
+                padding: scale(self.padding),
+                radius: scale(self.radius),
+                shadow: scale(self.shadow),
+                chrome: self.chrome,
+            }
+        }
+    }
+
+    pub fn presets() -> Vec<(&'static str, Background)> {
+        vec![
+            (
+                "Sunset",
+                Background::Gradient([255, 94, 98, 255], [255, 195, 113, 255]),
+            ),
+            (
+                "Ocean",
+                Background::Gradient([33, 147, 176, 255], [109, 213, 237, 255]),
+            ),
+            (
+                "Violet",
+                Background::Gradient([142, 45, 226, 255], [74, 0, 224, 255]),
+            ),
+            (
+                "Mint",
+                Background::Gradient([67, 233, 123, 255], [56, 249, 215, 255]),
+            ),
+            (
+                "Night",
+                Background::Gradient([15, 32, 39, 255], [44, 83, 100, 255]),
+            ),
+            ("White", Background::Solid([255, 255, 255, 255])),
+            ("Graphite", Background::Solid([40, 44, 52, 255])),
+        ]
+    }
+
+    pub fn load_background(path: &str) -> Result<DynamicImage, AppError> {
+        image::open(path).map_err(|e| AppError::Image(format!("{}: {}", path, e)))
+    }
+
+    // buffer is an encoded image, the result is a png at the resolution of the capture
+    pub fn apply(buffer: &[u8], framing: &Framing) -> Result<Vec<u8>, AppError> {
+        let image = image::load_from_memory(buffer)
+            .map_err(|e| AppError::Image(e.to_string()))?
+            .to_rgba8();
+        let background = match &framing.background {
+            Background::Image(path) => Some(load_background(path)?),
+            _ => None,
+        };
+        encode(frame(image, framing, background.as_ref()))
+    }
+
+    // background_image is the image of Background::Image, already loaded
+    pub fn frame(
+        image: RgbaImage,
+        framing: &Framing,
+        background_image: Option<&DynamicImage>,
+    ) -> RgbaImage {
+        let content = if framing.chrome {
+            chrome(&image)
+        } else {
+            image
+        };
+        let content = round_corners(content, framing.radius);
+        let width = content.width() + 2 * framing.padding;
+        let height = content.height() + 2 * framing.padding;
+        let mut canvas = match (&framing.background, background_image) {
+            (Background::Solid(color), _) => RgbaImage::from_pixel(width, height, Rgba(*color)),
+            (Background::Gradient(from, to), _) => gradient(width, height, *from, *to),
+            (Background::Image(_), Some(image)) => image
+                .resize_to_fill(width, height, FilterType::Lanczos3)
+                .to_rgba8(),
+            (Background::Image(_), None) => RgbaImage::new(width, height),
+        };
+        // the shadow comes with its own margin around the content
+        let shadowed = shadow(&content, framing.shadow);
+        let margin = (shadowed.width() - content.width()) / 2;
+        let position = framing.padding as i64 - margin as i64;
+        imageops::overlay(&mut canvas, &shadowed, position, position);
+        canvas
+    }
+
+    fn gradient(width: u32, height: u32, from: [u8; 4], to: [u8; 4]) -> RgbaImage {
+        let span = (width + height).saturating_sub(2).max(1) as f32;
+        RgbaImage::from_fn(width, height, |x, y| {
+            let t = (x + y) as f32 / span;
+            let mut pixel = [0; 4];
+            for (i, channel) in pixel.iter_mut().enumerate() {
+                *channel = (from[i] as f32 + (to[i] as f32 - from[i] as f32) * t).round() as u8;
+            }
+            Rgba(pixel)
+        })
+    }
+
+    // a window title bar like the one of macos, proportional to the width of the capture
+    fn chrome(image: &RgbaImage) -> RgbaImage {
+        let bar = (image.width() / 40).max(8);
+        let mut canvas = RgbaImage::from_pixel(
+            image.width(),
+            image.height() + bar,
+            Rgba([232, 232, 232, 255]),
+        );
+        let radius = bar as f32 * 0.22;
+        let dots = [[255, 95, 87], [254, 188, 46], [40, 200, 64]];
+        for (i, [r, g, b]) in dots.into_iter().enumerate() {
+            let cx = bar as f32 * (0.6 + 0.7 * i as f32);
+            let cy = bar as f32 / 2.0;
+            for y in 0..bar {
+                for x in 0..(cx + radius + 1.0).ceil().min(image.width() as f32) as u32 {
+                    let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                    let coverage = (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+                    if coverage > 0.0 {
+                        let pixel = canvas.get_pixel_mut(x, y);
+                        for (channel, dot) in pixel.0.iter_mut().zip([r, g, b]) {
+                            *channel = (*channel as f32 * (1.0 - coverage) + dot as f32 * coverage)
+                                .round() as u8;
+                        }
+                    }
+                }
+            }
+        }
+        imageops::overlay(&mut canvas, image, 0, bar as i64);
+        canvas
+    }
+
+    // a small copy of the capture, to see the frame while it is edited
+    pub struct Preview {
+        thumbnail: RgbaImage,
+        factor: f32,
+        background: Option<(String, DynamicImage)>,
+        shown: Option<(Framing, egui::TextureHandle)>,
+    }
+
+    impl Preview {
+        pub fn new(buffer: &[u8]) -> Result<Self, AppError> {
+            let image =
+                image::load_from_memory(buffer).map_err(|e| AppError::Image(e.to_string()))?;
+            let thumbnail = image.thumbnail(PREVIEW_WIDTH, PREVIEW_WIDTH).to_rgba8();
+            Ok(Preview {
+                factor: thumbnail.width() as f32 / image.width().max(1) as f32,
+                thumbnail,
+                background: None,
+                shown: None,
+            })
+        }
+
+        // rendered again only when the frame changes
+        pub fn texture(
+            &mut self,
+            ctx: &egui::Context,
+            framing: &Framing,
+        ) -> Result<egui::TextureHandle, AppError> {
+            if let Some((shown, texture)) = &self.shown {
+                if shown == framing {
+                    return Ok(texture.clone());
+                }
+            }
+            let background = match &framing.background {
+                Background::Image(path) => {
+                    if self.background.as_ref().map(|(p, _)| p) != Some(path) {
+                        let image =
+                            load_background(path)?.thumbnail(2 * PREVIEW_WIDTH, 2 * PREVIEW_WIDTH);
+                        self.background = Some((path.clone(), image));
+                    }
+                    self.background.as_ref().map(|(_, image)| image)
+                }
+                _ => None,
+            };
+            let image = frame(
+                self.thumbnail.clone(),
+                &framing.scaled(self.factor),
+                background,
+            );
+            let size = [image.width() as usize, image.height() as usize];
+            let texture = ctx.load_texture(
+                "framing-preview",
+                egui::ColorImage::from_rgba_unmultiplied(size, image.as_flat_samples().as_slice()),
+                Default::default(),
+            );
+            self.shown = Some((framing.clone(), texture.clone()));
+            Ok(texture)
+        }
+    }
+}
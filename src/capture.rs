@@ -0,0 +1,335 @@
+pub mod capture {
+    use crate::cursor::cursor::{draw_cursor_on_png, grab_cursor};
+    use crate::error::error::AppError;
+    use crate::scroll::scroll::scroll_capture;
+    use crate::RectangleCrop;
+    use eframe::egui;
+    use screenshots::Screen;
+    use std::sync::mpsc::{self, Receiver, TryRecvError};
+    use std::time::{Duration, Instant};
+
+    const POLL: Duration = Duration::from_millis(10);
+    const HIDE_TIMEOUT: Duration = Duration::from_secs(2);
+    const HIDE_FALLBACK: Duration = Duration::from_millis(300); // when the window state can't be queried
+    const COMPOSITOR_DELAY: Duration = Duration::from_millis(50);
+    const CAPTURE_TIMEOUT: Duration = Duration::from_secs(10);
+    const SCROLL_TIMEOUT: Duration = Duration::from_secs(120);
+
+    // Idle -> Hiding -> WaitingForCompositor -> Capturing -> Showing -> Idle
+    enum CaptureState {
+        Idle,
+        Hiding {
+            since: Instant,
+            x11: Option<Box<X11>>, // none without x11, the window state is then guessed
+        },
+        WaitingForCompositor {
+            since: Instant,
+            frames: u32,
+        },
+        Capturing {
+            since: Instant,
+            timeout: Duration,
+            result: Receiver<Result<Vec<u8>, AppError>>,
+        },
+        Showing,
+    }
+
+    // what the app has to do after a step of the state machine
+    pub enum Step {
+        Idle,
+        Wait(Duration),
+        Capture,
+        Done(Result<Vec<u8>, AppError>),
+        Show,
+    }
+
+    pub struct Request {
+        pub screen: Screen,
+        pub rect: Option<RectangleCrop>,
+        pub scrolling: bool,
+        pub cursor: Option<bool>, // Some(halo) to draw the mouse cursor
+    }
+
+    pub struct Capture {
+        state: CaptureState,
+        compositor: bool,
+    }
+
+    impl Capture {
+        pub fn new() -> Self {
+            Capture {
+                state: CaptureState::Idle,
+                compositor: false,
+            }
+        }
+
+        // hide is false when the window itself has to be captured (annotations)
+        pub fn start(&mut self, hide: bool) {
+            let x11 = X11::connect();
+            // without x11 (wayland) there is always a compositor
+            self.compositor = x11.as_ref().is_none_or(|x11| x11.compositor_running());
+            self.state = if hide {
+                CaptureState::Hiding {
+                    since: Instant::now(),
+                    x11: x11.map(Box::new),
+                }
+            } else {
+                CaptureState::WaitingForCompositor {
+                    since: Instant::now(),
+                    frames: 0,
+                }
+            };
+        }
+
+        // shows the window again without capturing
+        pub fn reshow(&mut self) {
+            self.state = CaptureState::Showing;
+        }
+
+        pub fn is_idle(&self) -> bool {
+            matches!(self.state, CaptureState::Idle)
+        }
+
+        // called once per frame
+        pub fn step(&mut self) -> Step {
+            match &mut self.state {
+                CaptureState::Idle => Step::Idle,
+                CaptureState::Hiding { since, x11 } => {
+                    let elapsed = since.elapsed();
+                    let hidden = match x11.as_ref().and_then(|x11| x11.window_mapped()) {
+                        Some(mapped) => !mapped,
+                        None => elapsed >= HIDE_FALLBACK,
+                    };
+                    if hidden {
+                        self.state = CaptureState::WaitingForCompositor {
+                            since: Instant::now(),
+                            frames: 0,
+                        };
+                        Step::Wait(POLL)
+                    } else if elapsed >= HIDE_TIMEOUT {
+                        self.state = CaptureState::Showing;
+                        Step::Done(Err(AppError::WindowNotHidden))
+                    } else {
+                        Step::Wait(POLL)
+                    }
+                }
+                CaptureState::WaitingForCompositor { since, frames } => {
+                    // the compositor needs a couple of frames to remove the window from the screen
+                    *frames += 1;
+                    let delay = if self.compositor {
+                        COMPOSITOR_DELAY
+                    } else {
+                        Duration::ZERO
+                    };
+                    if *frames > 2 && since.elapsed() >= delay {
+                        Step::Capture
+                    } else {
+                        Step::Wait(POLL)
+                    }
+                }
+                CaptureState::Capturing {
+                    since,
+                    timeout,
+                    result,
+                } => match result.try_recv() {
+                    Ok(result) => {
+                        self.state = CaptureState::Showing;
+                        Step::Done(result)
+                    }
+                    Err(TryRecvError::Disconnected) => {
+                        self.state = CaptureState::Showing;
+                        Step::Done(Err(AppError::Capture(
+                            "the capture thread stopped".to_string(),
+                        )))
+                    }
+                    Err(TryRecvError::Empty) => {
+                        if since.elapsed() >= *timeout {
+                            self.state = CaptureState::Showing;
+                            Step::Done(Err(AppError::CaptureTimeout))
+                        } else {
+                            // the capture thread wakes up the ui when it is done
+                            Step::Wait(timeout.saturating_sub(since.elapsed()))
+                        }
+                    }
+                },
+                CaptureState::Showing => {
+                    self.state = CaptureState::Idle;
+                    Step::Show
+                }
+            }
+        }
+
+        // grabs the screen on its own thread, so the ui never freezes
+        pub fn run(&mut self, ctx: &egui::Context, request: Request) {
+            let (sender, receiver) = mpsc::channel();
+            let timeout = if request.scrolling {
+                SCROLL_TIMEOUT
+            } else {
+                CAPTURE_TIMEOUT
+            };
+            let ctx = ctx.clone();
+            std::thread::spawn(move || {
+                let _ = sender.send(request.grab());
+                ctx.request_repaint();
+            });
+            self.state = CaptureState::Capturing {
+                since: Instant::now(),
+                timeout,
+                result: receiver,
+            };
+        }
+    }
+
+    impl Request {
+        fn grab(self) -> Result<Vec<u8>, AppError> {
+            if let (Some(rect), true) = (self.rect, self.scrolling) {
+                if let Some(buffer) = scroll_capture(self.screen, rect) {
+                    return Ok(buffer);
+                }
+            }
+            let image = match self.rect {
+                Some(rect) => self.screen.capture_area(
+                    rect.x_left.floor() as i32,
+                    rect.y_left.floor() as i32,
+                    rect.width.floor() as u32,
+                    rect.height.floor() as u32,
+                ),
+                None => self.screen.capture(),
+            }
+            .map_err(|e| AppError::Capture(e.to_string()))?;
+            let buffer = image
+                .to_png(None)
+                .map_err(|e| AppError::Capture(e.to_string()))?;
+            if let (Some(halo), Some(cursor)) = (self.cursor, grab_cursor()) {
+                let display = self.screen.display_info;
+                let (origin, captured_width) = match self.rect {
+                    Some(rect) => (
+                        (
+                            display.x as f32 + rect.x_left.floor(),
+                            display.y as f32 + rect.y_left.floor(),
+                        ),
+                        rect.width.floor(),
+                    ),
+                    None => ((display.x as f32, display.y as f32), display.width as f32),
+                };
+                if let Some(with_cursor) =
+                    draw_cursor_on_png(&buffer, &cursor, origin, captured_width, halo)
+                {
+                    return Ok(with_cursor);
+                }
+            }
+            Ok(buffer)
+        }
+    }
+
+    // the connection to the x server of a capture, opened once and used by every poll
+    #[cfg(target_os = "linux")]
+    struct X11 {
+        conn: x11rb::rust_connection::RustConnection,
+        screen_num: usize,
+        root: u32,
+        pid_atom: u32,
+    }
+
+    #[cfg(target_os = "linux")]
+    impl X11 {
+        fn connect() -> Option<Self> {
+            use x11rb::connection::Connection;
+            use x11rb::protocol::xproto::ConnectionExt;
+
+            let (conn, screen_num) = x11rb::connect(None).ok()?;
+            let root = conn.setup().roots.get(screen_num)?.root;
+            let pid_atom = conn
+                .intern_atom(true, b"_NET_WM_PID")
+                .ok()?
+                .reply()
+                .ok()?
+                .atom;
+            Some(X11 {
+                conn,
+                screen_num,
+                root,
+                pid_atom,
+            })
+        }
+
+        // true if one of the windows of this process is visible on the screen
+        fn window_mapped(&self) -> Option<bool> {
+            use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, MapState};
+
+            let conn = &self.conn;
+            let pid = std::process::id();
+            let mut found = false;
+            // the window can be a few levels down when the window manager adds frames
+            let mut level = vec![self.root];
+            for _ in 0..3 {
+                let mut next = Vec::new();
+                for window in level {
+                    let children = match conn.query_tree(window).ok().and_then(|c| c.reply().ok())
+                    {
+                        Some(tree) => tree.children,
+                        None => continue,
+                    };
+                    for child in children {
+                        let owner = conn
+                            .get_property(false, child, self.pid_atom, AtomEnum::CARDINAL, 0, 1)
+                            .ok()
+                            .and_then(|c| c.reply().ok())
+                            .and_then(|p| p.value32().and_then(|mut v| v.next()));
+                        if owner == Some(pid) {
+                            found = true;
+                            let attributes =
+                                conn.get_window_attributes(child).ok()?.reply().ok()?;
+                            if attributes.map_state == MapState::VIEWABLE {
+                                return Some(true);
+                            }
+                        }
+                        next.push(child);
+                    }
+                }
+                level = next;
+            }
+            if found {
+                Some(false)
+            } else {
+                None
+            }
+        }
+
+        fn compositor_running(&self) -> bool {
+            use x11rb::protocol::xproto::ConnectionExt;
+
+            let check = || -> Option<bool> {
+                let name = format!("_NET_WM_CM_S{}", self.screen_num);
+                let atom = self
+                    .conn
+                    .intern_atom(false, name.as_bytes())
+                    .ok()?
+                    .reply()
+                    .ok()?
+                    .atom;
+                let owner = self.conn.get_selection_owner(atom).ok()?.reply().ok()?.owner;
+                Some(owner != x11rb::NONE)
+            };
+            check().unwrap_or(true)
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    struct X11;
+
+    #[cfg(not(target_os = "linux"))]
+    impl X11 {
+        fn connect() -> Option<Self> {
+            None
+        }
+
+        fn window_mapped(&self) -> Option<bool> {
+            None
+        }
+
+        fn compositor_running(&self) -> bool {
+            true
+        }
+    }
+}
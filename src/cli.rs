@@ -0,0 +1,86 @@
+pub mod cli {
+    use crate::adjust::adjust::{apply, encode_jpeg, Adjustment};
+    use crate::error::error::{write_file, AppError};
+    use crate::metadata::metadata::{embed, fields, parse_field};
+    use crate::project::project::{is_project, open, Metadata};
+    use crate::vector::vector::flatten;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    pub const USAGE: &str = "usage: screenshot [FILE] [--resize WIDTH] [--rotate 90|180|270] \
+[--flip horizontal|vertical] [--border WIDTH[:#rrggbb]] [--shadow SIZE] [--round RADIUS] \
+[--meta KEY=VALUE] [--output FILE]";
+
+    #[derive(Default)]
+    pub struct Args {
+        pub file: Option<PathBuf>,
+        pub output: Option<PathBuf>,
+        pub adjustments: Vec<Adjustment>, // in the order of the flags
+        pub meta: Vec<(String, String)>,  // embedded in the saved png and jpeg files
+    }
+
+    // the arguments after the program name
+    pub fn parse(args: &[String]) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') {
+                if parsed.file.is_some() {
+                    return Err(format!("only one file can be opened, found {}", arg));
+                }
+                parsed.file = Some(PathBuf::from(arg));
+                continue;
+            }
+            let value = args.next().ok_or(format!("{} needs a value", arg))?;
+            match arg.as_str() {
+                "-o" | "--output" => parsed.output = Some(PathBuf::from(value)),
+                "--meta" => parsed.meta.push(parse_field(value)?),
+                flag => parsed.adjustments.push(Adjustment::parse(flag, value)?),
+            }
+        }
+        if parsed.output.is_some() && parsed.file.is_none() {
+            return Err("--output needs a file to read".to_string());
+        }
+        Ok(parsed)
+    }
+
+    // applies the adjustments without opening the window; the format follows the extension
+    pub fn process(
+        input: &Path,
+        output: &Path,
+        adjustments: &[Adjustment],
+        meta: &[(String, String)],
+    ) -> Result<(), AppError> {
+        // a project knows where the capture comes from, an image does not
+        let (buffer, metadata) = if is_project(input) {
+            let project = open(input)?;
+            (flatten(&project)?, project.document.metadata)
+        } else {
+            let buffer = fs::read(input)
+                .map_err(|e| AppError::Image(format!("{}: {}", input.display(), e)))?;
+            (buffer, Metadata::default())
+        };
+        let buffer = apply(&buffer, adjustments)?;
+        let image = image::load_from_memory(&buffer).map_err(|e| AppError::Image(e.to_string()))?;
+        // nothing is embedded unless some field is given
+        let fields = if meta.is_empty() {
+            Vec::new()
+        } else {
+            fields(&metadata, "", "", false, meta)
+        };
+        let extension = output
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "png" => write_file(output, &embed(&buffer, &fields)?),
+            "jpg" | "jpeg" => {
+                let jpeg = encode_jpeg(&image.to_rgba8())?;
+                write_file(output, &embed(&jpeg, &fields)?)
+            }
+            _ => image
+                .save(output)
+                .map_err(|e| AppError::Save(output.to_path_buf(), e.to_string())),
+        }
+    }
+}
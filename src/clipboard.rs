@@ -0,0 +1,156 @@
+pub mod clipboard {
+    use crate::error::error::AppError;
+    use arboard::{Clipboard, ImageData};
+    use base64::Engine;
+    use std::borrow::Cow;
+    use std::fmt;
+    use std::path::{Path, PathBuf};
+
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum CopyFormat {
+        Image,
+        Path,
+        File,
+        DataUri,
+        Html,
+    }
+
+    pub enum Pasted {
+        Image(Vec<u8>), // encoded, not always a png
+        File(PathBuf),
+    }
+
+    impl CopyFormat {
+        pub fn all() -> Vec<CopyFormat> {
+            vec![
+                CopyFormat::Image,
+                CopyFormat::Path,
+                CopyFormat::File,
+                CopyFormat::DataUri,
+                CopyFormat::Html,
+            ]
+        }
+
+        // these formats point to the capture saved in a file
+        pub fn needs_file(self) -> bool {
+            matches!(self, CopyFormat::Path | CopyFormat::File)
+        }
+    }
+
+    impl fmt::Display for CopyFormat {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                CopyFormat::Image => write!(f, "Image"),
+                CopyFormat::Path => write!(f, "File path"),
+                CopyFormat::File => write!(f, "File (for file managers)"),
+                CopyFormat::DataUri => write!(f, "Data URI"),
+                CopyFormat::Html => write!(f, "HTML <img>"),
+            }
+        }
+    }
+
+    // buffer is a png; file is where it is saved, for the formats that need it
+    pub fn copy(buffer: &[u8], format: CopyFormat, file: Option<&Path>) -> Result<(), AppError> {
+        let error = |e: arboard::Error| AppError::Clipboard(e.to_string());
+        let mut clipboard = Clipboard::new().map_err(error)?;
+        match format {
+            CopyFormat::Image => {
+                let image = image::load_from_memory(buffer)
+                    .map_err(|e| AppError::Image(e.to_string()))?
+                    .to_rgba8();
+                clipboard
+                    .set_image(ImageData {
+                        width: image.width() as usize,
+                        height: image.height() as usize,
+                        bytes: Cow::from(image.as_raw()),
+                    })
+                    .map_err(error)
+            }
+            CopyFormat::Path | CopyFormat::File => {
+                let file =
+                    file.ok_or(AppError::Clipboard("the capture is not saved".to_string()))?;
+                if format == CopyFormat::Path {
+                    clipboard
+                        .set_text(file.display().to_string())
+                        .map_err(error)
+                } else {
+                    // text/uri-list on linux, a file drop on windows, file urls on macos
+                    clipboard.set().file_list(&[file]).map_err(error)
+                }
+            }
+            CopyFormat::DataUri => clipboard.set_text(data_uri(buffer)).map_err(error),
+            CopyFormat::Html => {
+                let (width, height) = image::io::Reader::new(std::io::Cursor::new(buffer))
+                    .with_guessed_format()
+                    .map_err(|e| AppError::Image(e.to_string()))?
+                    .into_dimensions()
+                    .map_err(|e| AppError::Image(e.to_string()))?;
+                let html = format!(
+                    r#"<img src="{}" width="{}" height="{}" alt="Screenshot">"#,
+                    data_uri(buffer),
+                    width,
+                    height
+                );
+                // editors that don't take html get the tag as text
+                clipboard.set_html(html.clone(), Some(html)).map_err(error)
+            }
+        }
+    }
+
+    // an image, a copied file, or a path or a data uri copied as text
+    pub fn paste() -> Result<Pasted, AppError> {
+        let error = |e: arboard::Error| AppError::Clipboard(e.to_string());
+        let mut clipboard = Clipboard::new().map_err(error)?;
+        if let Ok(image) = clipboard.get_image() {
+            let image = image::RgbaImage::from_raw(
+                image.width as u32,
+                image.height as u32,
+                image.bytes.into_owned(),
+            )
+            .ok_or(AppError::Image("the image in the clipboard is broken".to_string()))?;
+            let mut buffer = Vec::new();
+            image::DynamicImage::ImageRgba8(image)
+                .write_to(
+                    &mut std::io::Cursor::new(&mut buffer),
+                    image::ImageOutputFormat::Png,
+                )
+                .map_err(|e| AppError::Image(e.to_string()))?;
+            return Ok(Pasted::Image(buffer));
+        }
+        if let Some(file) = clipboard
+            .get()
+            .file_list()
+            .ok()
+            .and_then(|files| files.into_iter().next())
+        {
+            return Ok(Pasted::File(file));
+        }
+        let text = clipboard
+            .get_text()
+            .map_err(|_| AppError::Clipboard("there is no image in the clipboard".to_string()))?;
+        let text = text.trim();
+        if let Some(data) = text
+            .strip_prefix("data:image/")
+            .and_then(|t| t.split_once(";base64,"))
+            .map(|(_, data)| data)
+        {
+            let buffer = base64::engine::general_purpose::STANDARD
+                .decode(data.trim())
+                .map_err(|e| AppError::Image(e.to_string()))?;
+            return Ok(Pasted::Image(buffer));
+        }
+        let path = PathBuf::from(text.strip_prefix("file://").unwrap_or(text));
+        if path.is_file() {
+            Ok(Pasted::File(path))
+        } else {
+            Err(AppError::Clipboard("there is no image in the clipboard".to_string()))
+        }
+    }
+
+    pub fn data_uri(buffer: &[u8]) -> String {
+        format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(buffer)
+        )
+    }
+}
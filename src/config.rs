@@ -0,0 +1,96 @@
+pub mod config {
+    use crate::adjust::adjust::AdjustSettings;
+    use crate::beautify::beautify::Framing;
+    use crate::optimize::optimize::Optimization;
+    use crate::pipeline::pipeline::PostStep;
+    use crate::regions::regions::Region;
+    use crate::shortcut::shortcut::RegionShortcut;
+    use crate::template::template::DEFAULT_TEMPLATE;
+    use crate::timer::timer::CountdownPosition;
+    use serde::{Deserialize, Serialize};
+
+    pub const CONFIG_KEY: &str = "config";
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Config {
+        pub regions: Vec<Region>,
+        pub region_shortcuts: Vec<RegionShortcut>,
+        pub include_cursor: bool,
+        pub cursor_halo: bool,
+        pub record_fps: u32,
+        pub schedules: Vec<String>,
+        pub watch_rate: u32,
+        pub watch_threshold: f32,
+        pub watch_cooldown: u32,
+        pub tick_sound: bool,
+        pub tick_wav: String,
+        pub shutter_sound: bool,
+        pub shutter_wav: String,
+        pub flash: bool,
+        pub countdown_position: CountdownPosition,
+        pub after_capture: Vec<PostStep>,
+        pub editor: String,
+        pub command: String,
+        pub name_template: String,
+        pub name_counter: u32,
+        pub history_count: u32,
+        pub history_days: u32,
+        pub adjust: AdjustSettings,
+        pub framing: Framing,
+        pub png_optimization: Optimization,
+        pub embed_metadata: bool,
+        pub embed_hostname: bool,
+        pub description: String,
+        pub tags: String, // separated by commas
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Config {
+                regions: Vec::new(),
+                region_shortcuts: Vec::new(),
+                include_cursor: false,
+                cursor_halo: false,
+                record_fps: 10,
+                schedules: Vec::new(),
+                watch_rate: 2,
+                watch_threshold: 1.0,
+                watch_cooldown: 5,
+                tick_sound: false,
+                tick_wav: String::new(),
+                shutter_sound: false,
+                shutter_wav: String::new(),
+                flash: false,
+                countdown_position: CountdownPosition::TopRight,
+                after_capture: Vec::new(),
+                editor: String::new(),
+                command: String::new(),
+                name_template: DEFAULT_TEMPLATE.to_string(),
+                name_counter: 1,
+                history_count: 50,
+                history_days: 30,
+                adjust: AdjustSettings::default(),
+                framing: Framing::default(),
+                png_optimization: Optimization::Off,
+                embed_metadata: false,
+                embed_hostname: false,
+                description: String::new(),
+                tags: String::new(),
+            }
+        }
+    }
+
+    impl Config {
+        pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+            match storage {
+                Some(storage) => eframe::get_value(storage, CONFIG_KEY).unwrap_or_default(),
+                None => Config::default(),
+            }
+        }
+
+        pub fn store(&self, storage: &mut dyn eframe::Storage) {
+            eframe::set_value(storage, CONFIG_KEY, self);
+        }
+    }
+}
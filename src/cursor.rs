@@ -0,0 +1,124 @@
+pub mod cursor {
+    use image::{Rgba, RgbaImage};
+
+    pub struct CursorImage {
+        pub x: i32, // pointer position, in root window coordinates
+        pub y: i32,
+        pub width: u32,
+        pub height: u32,
+        pub xhot: u32,
+        pub yhot: u32,
+        pub pixels: Vec<u32>, // premultiplied ARGB
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn grab_cursor() -> Option<CursorImage> {
+        use x11rb::protocol::xfixes::ConnectionExt;
+
+        let (conn, _) = x11rb::connect(None).ok()?;
+        conn.xfixes_query_version(4, 0).ok()?.reply().ok()?;
+        let reply = conn.xfixes_get_cursor_image().ok()?.reply().ok()?;
+        Some(CursorImage {
+            x: reply.x as i32,
+            y: reply.y as i32,
+            width: reply.width as u32,
+            height: reply.height as u32,
+            xhot: reply.xhot as u32,
+            yhot: reply.yhot as u32,
+            pixels: reply.cursor_image,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn grab_cursor() -> Option<CursorImage> {
+        None
+    }
+
+    // decodes the png, draws the cursor on it and encodes it back
+    pub fn draw_cursor_on_png(
+        png: &[u8],
+        cursor: &CursorImage,
+        origin: (f32, f32),
+        captured_width: f32,
+        halo: bool,
+    ) -> Option<Vec<u8>> {
+        let mut image = image::load_from_memory(png).ok()?.to_rgba8();
+        let scale = image.width() as f32 / captured_width;
+        draw_cursor(&mut image, cursor, origin, scale, halo);
+        let mut output = Vec::new();
+        image
+            .write_to(
+                &mut std::io::Cursor::new(&mut output),
+                image::ImageOutputFormat::Png,
+            )
+            .ok()?;
+        Some(output)
+    }
+
+    // origin: top-left corner of the captured area, in root window coordinates
+    // scale: image pixels per root window unit
+    pub fn draw_cursor(
+        image: &mut RgbaImage,
+        cursor: &CursorImage,
+        origin: (f32, f32),
+        scale: f32,
+        halo: bool,
+    ) {
+        let hot_x = (cursor.x as f32 - origin.0) * scale;
+        let hot_y = (cursor.y as f32 - origin.1) * scale;
+
+        if halo {
+            let radius = 20.0 * scale;
+            blend_circle(image, (hot_x, hot_y), radius, Rgba([255, 220, 0, 90]));
+        }
+
+        let left = hot_x - cursor.xhot as f32 * scale;
+        let top = hot_y - cursor.yhot as f32 * scale;
+        let width = (cursor.width as f32 * scale).ceil() as i32;
+        let height = (cursor.height as f32 * scale).ceil() as i32;
+        for dy in 0..height {
+            for dx in 0..width {
+                let x = left as i32 + dx;
+                let y = top as i32 + dy;
+                if x < 0 || y < 0 || x >= image.width() as i32 || y >= image.height() as i32 {
+                    continue;
+                }
+                let sx = ((dx as f32 / scale) as u32).min(cursor.width - 1);
+                let sy = ((dy as f32 / scale) as u32).min(cursor.height - 1);
+                let argb = cursor.pixels[(sy * cursor.width + sx) as usize];
+                let a = (argb >> 24) & 0xff;
+                if a == 0 {
+                    continue;
+                }
+                let src = [(argb >> 16) & 0xff, (argb >> 8) & 0xff, argb & 0xff];
+                let dst = image.get_pixel_mut(x as u32, y as u32);
+                for (d, s) in dst.0.iter_mut().zip(src) {
+                    // the cursor image is premultiplied
+                    *d = (s + *d as u32 * (255 - a) / 255).min(255) as u8;
+                }
+                dst.0[3] = 255;
+            }
+        }
+    }
+
+    fn blend_circle(image: &mut RgbaImage, center: (f32, f32), radius: f32, color: Rgba<u8>) {
+        let alpha = color.0[3] as f32 / 255.0;
+        let x_min = (center.0 - radius).max(0.0) as u32;
+        let y_min = (center.1 - radius).max(0.0) as u32;
+        let x_max = ((center.0 + radius).ceil() as u32).min(image.width());
+        let y_max = ((center.1 + radius).ceil() as u32).min(image.height());
+        for y in y_min..y_max {
+            for x in x_min..x_max {
+                let dx = x as f32 + 0.5 - center.0;
+                let dy = y as f32 + 0.5 - center.1;
+                if dx * dx + dy * dy > radius * radius {
+                    continue;
+                }
+                let dst = image.get_pixel_mut(x, y);
+                for c in 0..3 {
+                    dst.0[c] = (color.0[c] as f32 * alpha + dst.0[c] as f32 * (1.0 - alpha)) as u8;
+                }
+            }
+        }
+    }
+}
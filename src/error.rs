@@ -0,0 +1,58 @@
+pub mod error {
+    use std::fmt;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    #[derive(Debug)]
+    pub enum AppError {
+        NoScreen,
+        Capture(String),
+        WindowNotHidden,
+        CaptureTimeout,
+        Dialog(String),
+        Save(PathBuf, String),
+        Clipboard(String),
+        Image(String),
+        Recording(String),
+        Command(String),
+        Project(String),
+    }
+
+    impl fmt::Display for AppError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                AppError::NoScreen => write!(f, "No screen found"),
+                AppError::Capture(e) => write!(f, "Cannot capture the screen: {}", e),
+                AppError::WindowNotHidden => write!(f, "The window could not be hidden"),
+                AppError::CaptureTimeout => write!(f, "The capture timed out"),
+                AppError::Dialog(e) => write!(f, "Cannot open the file dialog: {}", e),
+                AppError::Save(path, e) => write!(f, "Cannot save {}: {}", path.display(), e),
+                AppError::Clipboard(e) => write!(f, "Cannot copy to the clipboard: {}", e),
+                AppError::Image(e) => write!(f, "Invalid image: {}", e),
+                AppError::Recording(e) => write!(f, "Cannot save the recording: {}", e),
+                AppError::Command(e) => write!(f, "After capture action failed: {}", e),
+                AppError::Project(e) => write!(f, "Invalid project file: {}", e),
+            }
+        }
+    }
+
+    impl std::error::Error for AppError {}
+
+    pub fn recovery_dir() -> PathBuf {
+        std::env::temp_dir().join("screenshot-recovery")
+    }
+
+    // keeps a copy of a capture that could not be saved or copied
+    pub fn recovery_save(buffer: &[u8]) -> Option<PathBuf> {
+        let dir = recovery_dir();
+        fs::create_dir_all(&dir).ok()?;
+        let name = chrono::Local::now().format("recovered-%Y%m%d-%H%M%S%.3f.png");
+        let path = dir.join(name.to_string());
+        fs::write(&path, buffer).ok()?;
+        Some(path)
+    }
+
+    pub fn write_file(path: &Path, buffer: &[u8]) -> Result<(), AppError> {
+        fs::write(path, buffer).map_err(|e| AppError::Save(path.to_path_buf(), e.to_string()))
+    }
+}
@@ -0,0 +1,212 @@
+pub mod history {
+    use crate::error::error::AppError;
+    use chrono::{Local, TimeZone};
+    use eframe::egui;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    const THUMBNAIL_SIZE: u32 = 160;
+
+    // stored as <id>.json next to <id>.png and the thumbnail <id>.thumb.png
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct HistoryEntry {
+        pub id: String,
+        pub timestamp: i64, // milliseconds since the epoch
+        pub screen: usize,
+        pub mode: String,
+        pub width: u32,
+        pub height: u32,
+    }
+
+    pub struct History {
+        dir: PathBuf,
+        entries: Vec<HistoryEntry>, // newest first
+        thumbnails: HashMap<String, egui::TextureHandle>,
+        pub max_count: u32, // 0 -> no limit
+        pub max_days: u32,  // 0 -> no limit
+    }
+
+    impl HistoryEntry {
+        pub fn time(&self) -> String {
+            match Local.timestamp_millis_opt(self.timestamp).single() {
+                Some(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                None => String::new(),
+            }
+        }
+    }
+
+    // empty, nothing is read or deleted until the limits are known
+    impl Default for History {
+        fn default() -> Self {
+            History {
+                dir: data_dir().join("history"),
+                entries: Vec::new(),
+                thumbnails: HashMap::new(),
+                max_count: 50,
+                max_days: 30,
+            }
+        }
+    }
+
+    impl History {
+        // loads the saved captures, dropping the ones beyond the limits
+        pub fn new(max_count: u32, max_days: u32) -> Self {
+            let mut history = History {
+                max_count,
+                max_days,
+                ..History::default()
+            };
+            history.load();
+            history
+        }
+
+        fn load(&mut self) {
+            let files = match fs::read_dir(&self.dir) {
+                Ok(files) => files,
+                Err(_) => return,
+            };
+            for file in files.flatten() {
+                let path = file.path();
+                if path.extension().is_some_and(|e| e == "json") {
+                    if let Some(entry) = fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|json| serde_json::from_str::<HistoryEntry>(&json).ok())
+                    {
+                        if self.image_path(&entry.id).exists() {
+                            self.entries.push(entry);
+                        }
+                    }
+                }
+            }
+            self.entries.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+            self.prune();
+        }
+
+        pub fn entries(&self) -> &Vec<HistoryEntry> {
+            &self.entries
+        }
+
+        fn image_path(&self, id: &str) -> PathBuf {
+            self.dir.join(format!("{}.png", id))
+        }
+
+        fn thumbnail_path(&self, id: &str) -> PathBuf {
+            self.dir.join(format!("{}.thumb.png", id))
+        }
+
+        pub fn add(&mut self, buffer: &[u8], screen: usize, mode: &str) -> Result<(), AppError> {
+            let image =
+                image::load_from_memory(buffer).map_err(|e| AppError::Image(e.to_string()))?;
+            let now = Local::now();
+            let mut id = now.format("%Y%m%d-%H%M%S-%3f").to_string();
+            while self.image_path(&id).exists() {
+                id.push('x');
+            }
+            let entry = HistoryEntry {
+                id: id.clone(),
+                timestamp: now.timestamp_millis(),
+                screen,
+                mode: mode.to_string(),
+                width: image.width(),
+                height: image.height(),
+            };
+            let save_error = |path: &Path, e: String| AppError::Save(path.to_path_buf(), e);
+            fs::create_dir_all(&self.dir).map_err(|e| save_error(&self.dir, e.to_string()))?;
+            let image_path = self.image_path(&id);
+            fs::write(&image_path, buffer).map_err(|e| save_error(&image_path, e.to_string()))?;
+            let thumbnail_path = self.thumbnail_path(&id);
+            image
+                .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+                .save(&thumbnail_path)
+                .map_err(|e| save_error(&thumbnail_path, e.to_string()))?;
+            let json_path = self.dir.join(format!("{}.json", id));
+            let json = serde_json::to_string_pretty(&entry)
+                .map_err(|e| save_error(&json_path, e.to_string()))?;
+            fs::write(&json_path, json).map_err(|e| save_error(&json_path, e.to_string()))?;
+            self.entries.insert(0, entry);
+            self.prune();
+            Ok(())
+        }
+
+        // drops the captures beyond the maximum count or age
+        pub fn prune(&mut self) {
+            // from the oldest, so the indices still to check do not move
+            for index in (0..self.entries.len()).rev() {
+                if self.beyond(index, self.max_count, self.max_days) {
+                    self.delete(index);
+                }
+            }
+        }
+
+        // how many captures prune would delete with these limits
+        pub fn beyond_limits(&self, max_count: u32, max_days: u32) -> usize {
+            (0..self.entries.len())
+                .filter(|&index| self.beyond(index, max_count, max_days))
+                .count()
+        }
+
+        fn beyond(&self, index: usize, max_count: u32, max_days: u32) -> bool {
+            let oldest = Local::now().timestamp_millis() - max_days as i64 * 24 * 3600 * 1000;
+            let too_many = max_count != 0 && index >= max_count as usize;
+            let too_old = max_days != 0 && self.entries[index].timestamp < oldest;
+            too_many || too_old
+        }
+
+        pub fn delete(&mut self, index: usize) {
+            if index < self.entries.len() {
+                let entry = self.entries.remove(index);
+                let _ = fs::remove_file(self.image_path(&entry.id));
+                let _ = fs::remove_file(self.thumbnail_path(&entry.id));
+                let _ = fs::remove_file(self.dir.join(format!("{}.json", entry.id)));
+                self.thumbnails.remove(&entry.id);
+            }
+        }
+
+        pub fn read(&self, index: usize) -> Result<Vec<u8>, AppError> {
+            let entry = self.entries.get(index).ok_or(AppError::Image(
+                "the capture is not in the history".to_string(),
+            ))?;
+            let path = self.image_path(&entry.id);
+            fs::read(&path).map_err(|e| AppError::Image(format!("{}: {}", path.display(), e)))
+        }
+
+        // thumbnails are loaded the first time they are shown
+        pub fn thumbnail(
+            &mut self,
+            ctx: &egui::Context,
+            index: usize,
+        ) -> Option<egui::TextureHandle> {
+            let id = self.entries.get(index)?.id.clone();
+            if let Some(texture) = self.thumbnails.get(&id) {
+                return Some(texture.clone());
+            }
+            let image = image::open(self.thumbnail_path(&id)).ok()?.to_rgba8();
+            let size = [image.width() as usize, image.height() as usize];
+            let texture = ctx.load_texture(
+                format!("history-{}", id),
+                egui::ColorImage::from_rgba_unmultiplied(size, image.as_flat_samples().as_slice()),
+                Default::default(),
+            );
+            self.thumbnails.insert(id, texture.clone());
+            Some(texture)
+        }
+    }
+
+    // where the application keeps its own files
+    pub fn data_dir() -> PathBuf {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let base = if cfg!(target_os = "windows") {
+            std::env::var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            home.map(|h| h.join("Library/Application Support"))
+        } else {
+            std::env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .or_else(|| home.map(|h| h.join(".local/share")))
+        };
+        base.unwrap_or_else(std::env::temp_dir)
+            .join("rust-grab-utility")
+    }
+}
@@ -1,40 +1,118 @@
-use arboard::{Clipboard, ImageData};
 use chrono::Local;
 use eframe::egui::{self};
 use egui::{Color32, RichText};
 use image;
 use native_dialog::FileDialog;
-use std::borrow::Cow;
-use std::time::Instant;
-use std::{fs, time::Duration};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::fs;
+use std::time::{Duration, Instant};
 
 mod action;
+mod adjust;
+mod assets;
+mod beautify;
+mod capture;
+mod cli;
+mod clipboard;
+mod config;
+mod pipeline;
+mod optimize;
+mod project;
+mod recorder;
+mod cursor;
+mod error;
+mod history;
+mod metadata;
+mod regions;
+mod schedule;
 mod schermi;
+mod scroll;
+mod selection;
 mod shortcut;
+mod template;
+mod sound;
+mod toast;
+mod vector;
 mod timer;
+mod watch;
 
 use action::Action;
 use action::AllActionArr;
+use adjust::adjust::{apply, encode_jpeg, AdjustSettings, Adjustment};
+use beautify::beautify::{presets, Background, Framing, Preview};
+use capture::capture::{Capture, Request, Step};
+use clipboard::clipboard::{copy, paste, CopyFormat, Pasted};
+use config::config::Config;
+use error::error::{recovery_save, write_file, AppError};
+use history::history::History;
+use metadata::metadata::{embed, fields};
+use optimize::optimize::{Optimization, Writer};
+use pipeline::pipeline::{temp_file, Pipeline, PostAction};
+use project::project::{
+    is_project, Annotations, Document, Magnifier, Metadata, Point, Project, Shape, Style,
+    EXTENSION,
+};
+use recorder::recorder::{Recorder, MAX_FRAMES};
+use regions::regions::{Region, Regions};
+use schedule::schedule::Schedules;
 use schermi::schermi::Schermi;
+use selection::selection::SelectionPreset;
 use shortcut::shortcut::AllKeyArr;
 use shortcut::shortcut::NewShortcut;
 use shortcut::shortcut::ShortcutSet;
-use timer::timer::Timer;
+use sound::sound::{play, Sound};
+use template::template::{active_window_title, render, NameContext, DEFAULT_TEMPLATE};
+use toast::toast::Toasts;
+use timer::timer::{format_duration, CountdownPosition, Interval, Timer};
+use vector::vector::{flatten, magnifier_link, pdf, svg};
+use watch::watch::Watcher;
 
 fn main() -> Result<(), eframe::Error> {
-    let icon: Vec<u8> = image::open("./icon.png")
-        .expect("Error")
-        .to_rgba8()
-        .to_vec();
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("--install-desktop") {
+        // screenshot --install-desktop [PREFIX], the prefix defaults to ~/.local
+        let prefix = args.get(2).map(PathBuf::from).or_else(assets::assets::default_prefix);
+        match prefix.map(|p| assets::assets::install_desktop_files(&p)) {
+            Some(Ok(files)) => files.iter().for_each(|f| println!("{}", f.display())),
+            Some(Err(error)) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            None => {
+                eprintln!("HOME is not set, pass the install prefix");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    // screenshot FILE opens an image or a project at start, after the adjustments in the flags;
+    // with --output the adjusted image is written without opening the window
+    let args = match cli::cli::parse(&args[1..]) {
+        Ok(args) => args,
+        Err(error) => {
+            // 2 for a wrong command line, 1 for a file that could not be processed
+            eprintln!("{}\n{}", error, cli::cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let (Some(file), Some(output)) = (&args.file, &args.output) {
+        match cli::cli::process(file, output, &args.adjustments, &args.meta) {
+            Ok(()) => println!("{}", output.display()),
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
 
     let options = eframe::NativeOptions {
         maximized: true,
         decorated: false,
-        icon_data: Some(eframe::IconData {
-            rgba: icon,
-            width: 256,
-            height: 256,
-        }),
+        // a broken icon is not a reason to stop the app
+        icon_data: assets::assets::icon(),
         transparent: true,
         resizable: false,
         ..Default::default()
@@ -43,7 +121,7 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "My egui App",
         options,
-        Box::new(|_cc| Box::<MyApp>::default()),
+        Box::new(|cc| Box::new(MyApp::new(cc, args.file, args.adjustments, args.meta))),
     )
 }
 
@@ -51,7 +129,7 @@ struct MyApp {
     texture: Option<egui::TextureHandle>,
     buffer: Option<Vec<u8>>,
     screen_rect: RectangleCrop,
-    window_hidden: bool,
+    capture: Capture,
     mode: bool,
     mode_radio: SelectionMode,
     image_viewer: bool,
@@ -60,19 +138,84 @@ struct MyApp {
     shortcut_set: ShortcutSet,
     schermi: Schermi,
     default_location: String,
-    mac_bug: bool,
+    png_optimization: Optimization,
+    png_writer: Writer,
+    embed_metadata: bool,
+    embed_hostname: bool,
+    meta: Vec<(String, String)>, // from --meta, added to the metadata of every save
+    description: String,
+    tags: String,
+    monitor_detected: bool,
+    last_window_size: Option<egui::Vec2>,
+    started: Instant,
     annotation: bool,
     selection_annotation: SelectionAnnotation,
     annotation_element: AnnotationElement,
     last_modify: Vec<SelectionAnnotation>,
     option: Options,
     new_shortcut: NewShortcut,
-    get_real_monitor: u8,
-    default_name: String,
-    default_name_sel: bool,
-    default_name_num: u32,
+    name_template: String,
+    name_counter: u32,
+    capture_info: CaptureInfo,
+    regions: Regions,
+    show_regions: bool,
+    history: History,
+    show_history: bool,
+    history_limits: (u32, u32), // edited in the window, applied only when confirmed
+    confirm_prune: Option<usize>,
+    crop_id: u32,
+    crop_start: Option<egui::Rect>,
+    open_at_start: Option<PathBuf>,
+    start_adjustments: Vec<Adjustment>,
+    adjust_settings: AdjustSettings,
+    adjusted: Vec<Adjusted>,
+    framing: Framing,
+    show_framing: bool,
+    framing_preview: Option<Preview>,
+    saved_path: Option<PathBuf>,
+    selection_preset: SelectionPreset,
+    selection_id: u32,
+    include_cursor: bool,
+    cursor_halo: bool,
+    scrolling: bool,
+    recorder: Option<Recorder>,
+    record_fps: u32,
+    record_after_timer: bool,
+    restore_window: Option<(egui::Pos2, egui::Vec2)>,
+    interval: Interval,
+    auto_save_capture: bool,
+    schedules: Schedules,
+    watcher: Option<Watcher>,
+    watch_rate: u32,
+    watch_threshold: f32,
+    watch_cooldown: u32,
+    tick_sound: bool,
+    tick_wav: String,
+    shutter_sound: bool,
+    shutter_wav: String,
+    flash: bool,
+    flash_start: Option<Instant>,
+    countdown_position: CountdownPosition,
+    last_tick: Option<u64>,
+    toasts: Toasts,
+    pipeline: Pipeline,
+}
+// what the file name template knows about the last capture
+struct CaptureInfo {
+    timestamp: i64,
+    screen: usize,
+    mode: &'static str,
+    width: u32,
+    height: u32,
+    window_title: String,
+}
+// the editor before an adjustment, which bakes the annotations into the image
+struct Adjusted {
+    buffer: Vec<u8>,
+    annotations: AnnotationElement,
+    last_modify: Vec<SelectionAnnotation>,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 struct RectangleCrop {
     x_left: f32,
     y_left: f32,
@@ -86,7 +229,7 @@ enum SelectionMode {
     Selection,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 enum SelectionAnnotation {
     NotSelected,
     Pen,
@@ -96,6 +239,8 @@ enum SelectionAnnotation {
     Crop,
     Line,
     Circle,
+    Magnifier,
+    Adjust,
 }
 
 #[derive(PartialEq)]
@@ -103,8 +248,13 @@ enum Options {
     Shortcut,
     Screen,
     Allocation,
+    Capture,
+    Schedules,
+    Timer,
+    AfterCapture,
 }
 
+#[derive(Clone)]
 struct AnnotationElement {
     stroke: egui::Stroke,
     pen: Vec<Vec<(egui::Pos2, egui::Stroke)>>,
@@ -115,6 +265,11 @@ struct AnnotationElement {
     text: Vec<(egui::Pos2, String, egui::Stroke)>,
     text2: String,
     pos_text: bool,
+    magnifier: Vec<(egui::Rect, egui::Rect, bool, egui::Stroke)>, // source, inset, circle
+    magnifier_origin: Option<egui::Pos2>,
+    magnifier_source: Option<egui::Rect>, // chosen, waiting for the inset
+    magnifier_zoom: f32,
+    magnifier_circle: bool,
 }
 
 impl Default for MyApp {
@@ -128,7 +283,7 @@ impl Default for MyApp {
                 width: 0.0,
                 height: 0.0,
             },
-            window_hidden: false,
+            capture: Capture::new(),
             mode: false,
             mode_radio: SelectionMode::Screen,
             image_viewer: false,
@@ -136,8 +291,17 @@ impl Default for MyApp {
             show_options: false,
             shortcut_set: ShortcutSet::default(),
             default_location: "screenshots".to_string(),
+            png_optimization: Optimization::Off,
+            png_writer: Writer::new(),
+            embed_metadata: false,
+            embed_hostname: false,
+            meta: Vec::new(),
+            description: String::new(),
+            tags: String::new(),
             schermi: Schermi::new(),
-            mac_bug: false,
+            monitor_detected: false,
+            last_window_size: None,
+            started: Instant::now(),
             selection_annotation: SelectionAnnotation::NotSelected,
             last_modify: Default::default(),
             annotation: false,
@@ -151,17 +315,936 @@ impl Default for MyApp {
                 stroke: egui::Stroke::new(1.0, egui::Color32::BLACK),
                 text2: "Edit this text".to_owned(),
                 pos_text: false,
+                magnifier: Default::default(),
+                magnifier_origin: None,
+                magnifier_source: None,
+                magnifier_zoom: 3.0,
+                magnifier_circle: true,
             },
             option: Options::Shortcut,
             new_shortcut: NewShortcut::default(),
-            get_real_monitor: 0,
-            default_name: compute_default_name(),
-            default_name_sel: true,
-            default_name_num: 0,
+            name_template: DEFAULT_TEMPLATE.to_string(),
+            name_counter: 1,
+            capture_info: CaptureInfo {
+                timestamp: 0,
+                screen: 0,
+                mode: "screen",
+                width: 0,
+                height: 0,
+                window_title: String::new(),
+            },
+            regions: Regions::new(Vec::new()),
+            show_regions: false,
+            history: History::default(),
+            show_history: false,
+            history_limits: (50, 30),
+            confirm_prune: None,
+            crop_id: 0,
+            crop_start: None,
+            open_at_start: None,
+            start_adjustments: Vec::new(),
+            adjust_settings: AdjustSettings::default(),
+            adjusted: Vec::new(),
+            framing: Framing::default(),
+            show_framing: false,
+            framing_preview: None,
+            saved_path: None,
+            selection_preset: SelectionPreset::Free,
+            selection_id: 0,
+            include_cursor: false,
+            cursor_halo: false,
+            scrolling: false,
+            recorder: None,
+            record_fps: 10,
+            record_after_timer: false,
+            restore_window: None,
+            interval: Interval::new(),
+            auto_save_capture: false,
+            schedules: Schedules::new(Vec::new()),
+            watcher: None,
+            watch_rate: 2,
+            watch_threshold: 1.0,
+            watch_cooldown: 5,
+            tick_sound: false,
+            tick_wav: String::new(),
+            shutter_sound: false,
+            shutter_wav: String::new(),
+            flash: false,
+            flash_start: None,
+            countdown_position: CountdownPosition::TopRight,
+            last_tick: None,
+            toasts: Toasts::new(),
+            pipeline: Pipeline::new(Vec::new(), String::new(), String::new()),
         }
     }
 }
 impl MyApp {
+    fn new(
+        cc: &eframe::CreationContext<'_>,
+        open_at_start: Option<PathBuf>,
+        start_adjustments: Vec<Adjustment>,
+        meta: Vec<(String, String)>,
+    ) -> Self {
+        let config = Config::load(cc.storage);
+        let regions = Regions::new(config.regions);
+        let mut shortcut_set = ShortcutSet::default();
+        shortcut_set.load_region_shortcuts(&config.region_shortcuts, regions.list());
+        let mut app = MyApp {
+            open_at_start,
+            start_adjustments,
+            adjust_settings: config.adjust,
+            framing: config.framing,
+            png_optimization: config.png_optimization,
+            embed_metadata: config.embed_metadata,
+            embed_hostname: config.embed_hostname,
+            meta,
+            description: config.description,
+            tags: config.tags,
+            regions,
+            shortcut_set,
+            include_cursor: config.include_cursor,
+            cursor_halo: config.cursor_halo,
+            record_fps: config.record_fps,
+            schedules: Schedules::new(config.schedules),
+            watch_rate: config.watch_rate,
+            watch_threshold: config.watch_threshold,
+            watch_cooldown: config.watch_cooldown,
+            tick_sound: config.tick_sound,
+            tick_wav: config.tick_wav,
+            shutter_sound: config.shutter_sound,
+            shutter_wav: config.shutter_wav,
+            flash: config.flash,
+            countdown_position: config.countdown_position,
+            name_template: config.name_template,
+            name_counter: config.name_counter,
+            pipeline: Pipeline::new(config.after_capture, config.editor, config.command),
+            history: History::new(config.history_count, config.history_days),
+            history_limits: (config.history_count, config.history_days),
+            ..MyApp::default()
+        };
+        if app.schermi.no_screens() == 0 {
+            app.toasts.error(AppError::NoScreen.to_string());
+        }
+        app
+    }
+
+    fn capture_region(&mut self, region: Region, frame: &mut eframe::Frame) {
+        if region.screen_no < self.schermi.no_screens() {
+            self.timer.cancel_timer();
+            self.regions.set_pending(region);
+            self.start_capture(frame);
+        }
+    }
+
+    fn start_capture(&mut self, frame: &mut eframe::Frame) {
+        if self.capture.is_idle() {
+            self.capture.start(true);
+            frame.set_visible(false);
+        }
+    }
+
+    // shows the error; a capture that would be lost is kept in the recovery directory
+    fn report(&mut self, error: AppError, unsaved: Option<&[u8]>) {
+        let mut text = error.to_string();
+        if let Some(path) = unsaved.and_then(recovery_save) {
+            text = format!("{}\nA copy was saved in {}", text, path.display());
+        }
+        self.toasts.error(text);
+    }
+
+    // what to capture, from the current selection or the pending saved region
+    fn capture_request(&mut self) -> Result<Request, AppError> {
+        let mut screen = self.schermi.get_screen();
+        let mut rect = None;
+        if self.annotation {
+            screen = self.schermi.get_default_screen();
+        }
+        if self.mode || self.annotation {
+            rect = Some(self.screen_rect);
+        }
+        let mut mode = if rect.is_some() { "selection" } else { "screen" };
+        if let Some(region) = self.regions.take_pending() {
+            self.schermi.screen_no = region.screen_no;
+            screen = self.schermi.get_screen();
+            rect = region.rect;
+            mode = "region";
+        }
+        if !self.annotation {
+            self.regions.set_last(self.schermi.screen_no, rect);
+            // our window is hidden, the focus is on the window being captured
+            self.capture_info.screen = self.schermi.screen_no;
+            self.capture_info.mode = if self.scrolling { "scroll" } else { mode };
+            self.capture_info.window_title = active_window_title().unwrap_or_default();
+        }
+        let scrolling = self.scrolling;
+        self.scrolling = false;
+        Ok(Request {
+            screen: screen?,
+            rect,
+            scrolling,
+            cursor: if self.include_cursor && !self.annotation {
+                Some(self.cursor_halo)
+            } else {
+                None
+            },
+        })
+    }
+
+    fn captured(&mut self, buffer: Vec<u8>, ctx: &egui::Context) {
+        if !self.annotation {
+            if self.shutter_sound {
+                play(Sound::Shutter, &self.shutter_wav);
+            }
+            if self.flash {
+                self.flash_start = Some(Instant::now());
+            }
+        }
+        if let Ok((width, height)) = image_size(&buffer) {
+            self.capture_info.width = width;
+            self.capture_info.height = height;
+        }
+        if !self.annotation {
+            self.capture_info.timestamp = Local::now().timestamp_millis();
+        }
+        let automatic = self.auto_save_capture;
+        let mut saved = None;
+        if automatic {
+            self.auto_save_capture = false;
+            match self.save_to_default_location(&buffer) {
+                Ok((path, _)) => saved = Some(path),
+                Err(error) => self.report(error, Some(&buffer)),
+            }
+            self.interval.check_finished();
+        }
+        if !self.annotation {
+            if let Err(error) =
+                self.history
+                    .add(&buffer, self.capture_info.screen, self.capture_info.mode)
+            {
+                self.report(error, None);
+            }
+            saved = self.after_capture(&buffer, saved);
+        }
+        if automatic {
+            return;
+        }
+        self.show_image(buffer, ctx);
+        self.saved_path = saved;
+    }
+
+    // puts the image in the viewer, forgetting the annotations of the previous one
+    fn show_image(&mut self, buffer: Vec<u8>, ctx: &egui::Context) {
+        let image = match load_image_from_memory(&buffer) {
+            Ok(image) => image,
+            Err(error) => {
+                self.report(AppError::Image(error.to_string()), Some(&buffer));
+                return;
+            }
+        };
+        self.buffer = Some(buffer);
+        self.texture = Some(ctx.load_texture("my-image", image, Default::default()));
+        self.image_viewer = true;
+        self.mode = false;
+        self.annotation = false;
+        self.show_history = false;
+        self.show_options = false;
+        self.crop_start = None;
+        self.saved_path = None;
+        self.annotation_element.pen.clear();
+        self.annotation_element.rect.clear();
+        self.annotation_element.text.clear();
+        self.annotation_element.arrow.clear();
+        self.annotation_element.line.clear();
+        self.annotation_element.circle.clear();
+        self.annotation_element.magnifier.clear();
+        self.annotation_element.magnifier_source = None;
+        self.last_modify.clear();
+        self.adjusted.clear();
+        self.show_framing = false;
+        self.framing_preview = None;
+    }
+
+    // replaces the image in the viewer, keeping the editor open
+    fn replace_image(&mut self, buffer: Vec<u8>, ctx: &egui::Context) -> Result<(), AppError> {
+        let image =
+            load_image_from_memory(&buffer).map_err(|e| AppError::Image(e.to_string()))?;
+        if let Ok((width, height)) = image_size(&buffer) {
+            self.capture_info.width = width;
+            self.capture_info.height = height;
+        }
+        self.texture = Some(ctx.load_texture("my-image", image, Default::default()));
+        self.buffer = Some(buffer);
+        self.saved_path = None;
+        self.framing_preview = None;
+        Ok(())
+    }
+
+    // an undoable step of the editor; the annotations so far become part of the image,
+    // at its own resolution, so they follow the rotations and the resizes
+    fn adjust_image(
+        &mut self,
+        adjust: impl FnOnce(&[u8]) -> Result<Vec<u8>, AppError>,
+        ctx: &egui::Context,
+        frame: &eframe::Frame,
+    ) {
+        let (original, current) = match (self.buffer.clone(), self.current_image(frame)) {
+            (Some(original), Ok(Some(current))) => (original, current),
+            (_, Err(error)) => return self.report(error, None),
+            _ => return,
+        };
+        let result = adjust(&current).and_then(|buffer| self.replace_image(buffer, ctx));
+        if let Err(error) = result {
+            return self.report(error, None);
+        }
+        let annotations = self.annotation_element.clone();
+        self.annotation_element.pen.clear();
+        self.annotation_element.rect.clear();
+        self.annotation_element.text.clear();
+        self.annotation_element.arrow.clear();
+        self.annotation_element.line.clear();
+        self.annotation_element.circle.clear();
+        self.annotation_element.magnifier.clear();
+        self.adjusted.push(Adjusted {
+            buffer: original,
+            annotations,
+            last_modify: std::mem::take(&mut self.last_modify),
+        });
+        self.last_modify.push(SelectionAnnotation::Adjust);
+        // the crop selection would not match the new image
+        if self.selection_annotation == SelectionAnnotation::Crop {
+            self.selection_annotation = SelectionAnnotation::NotSelected;
+        }
+    }
+
+    fn undo_adjustment(&mut self, ctx: &egui::Context) {
+        if let Some(adjusted) = self.adjusted.pop() {
+            if let Err(error) = self.replace_image(adjusted.buffer, ctx) {
+                return self.report(error, None);
+            }
+            let element = &mut self.annotation_element;
+            element.pen = adjusted.annotations.pen;
+            element.rect = adjusted.annotations.rect;
+            element.text = adjusted.annotations.text;
+            element.arrow = adjusted.annotations.arrow;
+            element.line = adjusted.annotations.line;
+            element.circle = adjusted.annotations.circle;
+            element.magnifier = adjusted.annotations.magnifier;
+            self.last_modify = adjusted.last_modify;
+        }
+    }
+
+    // where the image is drawn in the viewer
+    fn image_rect(&self, frame: &eframe::Frame) -> Option<egui::Rect> {
+        let size = self.texture.as_ref()?.size_vec2();
+        let window = frame.info().window_info.size;
+        let dim_image =
+            resize_image_to_fit_container(window.x / 3.0 * 2.0, window.y / 3.0 * 2.0, size.x, size.y);
+        Some(egui::Rect::from_center_size(
+            egui::Pos2::new(window.x / 2.0, window.y / 2.0),
+            egui::Vec2::new(dim_image.0, dim_image.1),
+        ))
+    }
+
+    // the annotations are kept in pixels of the image, so they don't depend on the window
+    fn project(&self, frame: &eframe::Frame) -> Option<Project> {
+        let image_rect = self.image_rect(frame)?;
+        let scale = self.texture.as_ref()?.size_vec2().x / image_rect.width();
+        let point = |p: egui::Pos2| Point {
+            x: (p.x - image_rect.left()) * scale,
+            y: (p.y - image_rect.top()) * scale,
+        };
+        let style = |s: egui::Stroke| Style {
+            width: s.width * scale,
+            color: s.color.to_array(),
+        };
+        let shapes = |lines: &Vec<Vec<(egui::Pos2, egui::Stroke)>>| -> Vec<Shape> {
+            lines
+                .iter()
+                .filter(|line| !line.is_empty())
+                .map(|line| Shape {
+                    points: line.iter().map(|p| point(p.0)).collect(),
+                    style: style(line[0].1),
+                })
+                .collect()
+        };
+        let annotations = Annotations {
+            pen: shapes(&self.annotation_element.pen),
+            line: shapes(&self.annotation_element.line),
+            arrow: shapes(&self.annotation_element.arrow),
+            rect: shapes(&self.annotation_element.rect),
+            circle: shapes(&self.annotation_element.circle),
+            text: self
+                .annotation_element
+                .text
+                .iter()
+                .map(|t| project::project::Text {
+                    position: point(t.0),
+                    text: t.1.clone(),
+                    style: style(t.2),
+                })
+                .collect(),
+            magnifier: self
+                .annotation_element
+                .magnifier
+                .iter()
+                .map(|&(source, inset, circle, stroke)| Magnifier {
+                    source: pixel_rect(source, &point),
+                    inset: pixel_rect(inset, &point),
+                    circle,
+                    style: style(stroke),
+                })
+                .collect(),
+        };
+        // the crop selection is in pixels of the screen, like the screen selection
+        let crop = if self.selection_annotation == SelectionAnnotation::Crop {
+            let (adj, mc_adj) = screen_adjustment(frame);
+            let min = point(egui::Pos2::new(
+                self.screen_rect.x_left / adj,
+                self.screen_rect.y_left / adj - mc_adj,
+            ));
+            Some(project::project::Rect {
+                x: min.x,
+                y: min.y,
+                width: self.screen_rect.width / adj * scale,
+                height: self.screen_rect.height / adj * scale,
+            })
+        } else {
+            None
+        };
+        Some(Project {
+            image: self.buffer.clone()?,
+            document: Document::new(self.metadata(), annotations, crop),
+        })
+    }
+
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            timestamp: self.capture_info.timestamp,
+            screen: self.capture_info.screen,
+            mode: self.capture_info.mode.to_string(),
+            width: self.capture_info.width,
+            height: self.capture_info.height,
+            window_title: self.capture_info.window_title.clone(),
+        }
+    }
+
+    // what is embedded in the saved png and jpeg files; with the option off
+    // only the fields given with --meta, when there are some
+    fn metadata_fields(&self, metadata: &Metadata) -> Vec<(String, String)> {
+        if !self.embed_metadata && self.meta.is_empty() {
+            return Vec::new();
+        }
+        if !self.embed_metadata {
+            return fields(metadata, "", "", false, &self.meta);
+        }
+        fields(
+            metadata,
+            &self.description,
+            &self.tags,
+            self.embed_hostname,
+            &self.meta,
+        )
+    }
+
+    // opens the project in the annotation editor, with the annotations still editable
+    fn load_project(&mut self, project: Project, ctx: &egui::Context, frame: &eframe::Frame) {
+        let Document {
+            metadata,
+            annotations,
+            crop,
+            ..
+        } = project.document;
+        self.show_image(project.image, ctx);
+        let (image_rect, size) = match (self.image_rect(frame), &self.texture) {
+            (Some(image_rect), Some(texture)) if self.image_viewer => {
+                (image_rect, texture.size_vec2())
+            }
+            _ => return,
+        };
+        self.capture_info = CaptureInfo {
+            timestamp: metadata.timestamp,
+            screen: metadata.screen,
+            mode: match metadata.mode.as_str() {
+                "selection" => "selection",
+                "region" => "region",
+                "scroll" => "scroll",
+                _ => "screen",
+            },
+            width: metadata.width,
+            height: metadata.height,
+            window_title: metadata.window_title,
+        };
+        let scale = image_rect.width() / size.x;
+        let pos = |p: Point| {
+            egui::Pos2::new(image_rect.left() + p.x * scale, image_rect.top() + p.y * scale)
+        };
+        let stroke = |s: Style| {
+            egui::Stroke::new(
+                s.width * scale,
+                egui::Color32::from_rgba_premultiplied(s.color[0], s.color[1], s.color[2], s.color[3]),
+            )
+        };
+        let mut last_modify = Vec::new();
+        let mut lines = |shapes: Vec<Shape>, kind: SelectionAnnotation| {
+            let mut lines: Vec<Vec<(egui::Pos2, egui::Stroke)>> = Vec::new();
+            for shape in shapes {
+                let s = stroke(shape.style);
+                lines.push(shape.points.into_iter().map(|p| (pos(p), s)).collect());
+                last_modify.push(kind);
+            }
+            // the editor keeps an empty line at the end for the next one
+            if !lines.is_empty() {
+                lines.push(vec![]);
+            }
+            lines
+        };
+        self.annotation_element.pen = lines(annotations.pen, SelectionAnnotation::Pen);
+        self.annotation_element.line = lines(annotations.line, SelectionAnnotation::Line);
+        self.annotation_element.arrow = lines(annotations.arrow, SelectionAnnotation::Arrow);
+        self.annotation_element.rect = lines(annotations.rect, SelectionAnnotation::Rect);
+        self.annotation_element.circle = lines(annotations.circle, SelectionAnnotation::Circle);
+        for text in annotations.text {
+            self.annotation_element
+                .text
+                .push((pos(text.position), text.text, stroke(text.style)));
+            last_modify.push(SelectionAnnotation::Text);
+        }
+        let rect = |r: project::project::Rect| {
+            egui::Rect::from_min_size(
+                pos(Point { x: r.x, y: r.y }),
+                egui::vec2(r.width * scale, r.height * scale),
+            )
+        };
+        for magnifier in annotations.magnifier {
+            self.annotation_element.magnifier.push((
+                rect(magnifier.source),
+                rect(magnifier.inset),
+                magnifier.circle,
+                stroke(magnifier.style),
+            ));
+            last_modify.push(SelectionAnnotation::Magnifier);
+        }
+        self.last_modify = last_modify;
+        self.selection_annotation = SelectionAnnotation::NotSelected;
+        if let Some(crop) = crop {
+            self.crop_start = Some(egui::Rect::from_min_size(
+                pos(Point {
+                    x: crop.x,
+                    y: crop.y,
+                }),
+                egui::vec2(crop.width * scale, crop.height * scale),
+            ));
+            self.crop_id += 1;
+            self.selection_annotation = SelectionAnnotation::Crop;
+        }
+        self.annotation = true;
+    }
+
+    // images that were not captured here, opened in the annotation editor
+    fn load_image(&mut self, buffer: &[u8], mode: &'static str, ctx: &egui::Context) {
+        let buffer = match to_png(buffer) {
+            Ok(buffer) => buffer,
+            Err(error) => return self.report(error, None),
+        };
+        let (width, height) = image_size(&buffer).unwrap_or((0, 0));
+        self.capture_info = CaptureInfo {
+            timestamp: Local::now().timestamp_millis(),
+            screen: self.schermi.screen_no,
+            mode,
+            width,
+            height,
+            window_title: String::new(),
+        };
+        self.show_image(buffer, ctx);
+        self.annotation = self.image_viewer;
+    }
+
+    fn open_file(&mut self, path: &std::path::Path, ctx: &egui::Context, frame: &eframe::Frame) {
+        if is_project(path) {
+            return self.open_project(path, ctx, frame);
+        }
+        match fs::read(path) {
+            Ok(buffer) => self.load_image(&buffer, "file", ctx),
+            Err(error) => self.report(
+                AppError::Image(format!("{}: {}", path.display(), error)),
+                None,
+            ),
+        }
+    }
+
+    fn open_project(&mut self, path: &std::path::Path, ctx: &egui::Context, frame: &eframe::Frame) {
+        match project::project::open(path) {
+            Ok(project) => self.load_project(project, ctx, frame),
+            Err(error) => self.report(error, None),
+        }
+    }
+
+    // png files are optimized first, as chosen in the options; returns the message to show
+    // the optimized file replaces this one later, see update()
+    fn write_png(
+        &mut self,
+        path: &std::path::Path,
+        buffer: &[u8],
+        metadata: &Metadata,
+    ) -> Result<String, AppError> {
+        let fields = self.metadata_fields(metadata);
+        self.png_writer
+            .write(path, buffer, self.png_optimization, fields)?;
+        Ok(format!("Saved {}", path.display()))
+    }
+
+    // svg and pdf keep the annotations as vectors over the capture
+    fn write_image(
+        &mut self,
+        path: &std::path::Path,
+        buffer: &[u8],
+        frame: &eframe::Frame,
+    ) -> Result<String, AppError> {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "svg" | "pdf" => {
+                let project = self
+                    .project(frame)
+                    .ok_or(AppError::Image("no capture to export".to_string()))?;
+                let bytes = if extension == "svg" {
+                    svg(&project)?.into_bytes()
+                } else {
+                    pdf(&project)?
+                };
+                write_file(path, &bytes)?;
+                Ok(format!("Saved {}", path.display()))
+            }
+            "png" => {
+                let metadata = self.metadata();
+                self.write_png(path, buffer, &metadata)
+            }
+            "jpg" | "jpeg" => {
+                let image = image::load_from_memory(buffer)
+                    .map_err(|e| AppError::Image(e.to_string()))?;
+                let jpeg = encode_jpeg(&image.to_rgba8())?;
+                write_file(path, &embed(&jpeg, &self.metadata_fields(&self.metadata()))?)?;
+                Ok(format!("Saved {}", path.display()))
+            }
+            _ => write_file(path, buffer).map(|()| format!("Saved {}", path.display())),
+        }
+    }
+
+    fn export_vector(&mut self, frame: &eframe::Frame) {
+        let buffer = match self.buffer.clone() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        match self.choose_save_path(&[("SVG Image", &["svg"]), ("PDF Document", &["pdf"])]) {
+            Ok(Some(mut path)) => {
+                if !matches!(
+                    path.extension().and_then(|e| e.to_str()),
+                    Some("svg") | Some("pdf")
+                ) {
+                    path.set_extension("svg");
+                }
+                match self.write_image(&path, &buffer, frame) {
+                    Ok(message) => {
+                        self.name_used();
+                        self.toasts.info(message)
+                    }
+                    Err(error) => self.report(error, None),
+                }
+            }
+            Ok(None) => {}
+            Err(error) => self.report(error, None),
+        }
+    }
+
+    fn has_annotations(&self) -> bool {
+        let element = &self.annotation_element;
+        [
+            &element.pen,
+            &element.line,
+            &element.arrow,
+            &element.rect,
+            &element.circle,
+        ]
+        .iter()
+        .any(|lines| lines.iter().any(|line| !line.is_empty()))
+            || !element.text.is_empty()
+            || !element.magnifier.is_empty()
+    }
+
+    // the capture with the annotations that are being edited, if any
+    fn current_image(&self, frame: &eframe::Frame) -> Result<Option<Vec<u8>>, AppError> {
+        if self.annotation && self.has_annotations() {
+            if let Some(project) = self.project(frame) {
+                return flatten(&project).map(Some);
+            }
+        }
+        Ok(self.buffer.clone())
+    }
+
+    fn copy_as(&mut self, format: CopyFormat, frame: &eframe::Frame) {
+        let buffer = match self.current_image(frame) {
+            Ok(Some(buffer)) => buffer,
+            Ok(None) => return,
+            Err(error) => return self.report(error, None),
+        };
+        // the saved file is used only if it is what the viewer shows
+        let annotated = self.annotation && self.has_annotations();
+        let file = match (&self.saved_path, format.needs_file()) {
+            (_, false) => None,
+            (Some(path), true) if !annotated => Some(path.clone()),
+            (_, true) => match temp_file(&buffer) {
+                Ok(path) => Some(path),
+                Err(error) => return self.report(error, Some(&buffer)),
+            },
+        };
+        if let Err(error) = copy(&buffer, format, file.as_deref()) {
+            self.report(error, Some(&buffer));
+        }
+    }
+
+    fn save_project(&mut self, frame: &eframe::Frame) {
+        let project = match self.project(frame) {
+            Some(project) => project,
+            None => return,
+        };
+        match self.choose_save_path(&[("Rust Grab project", &[EXTENSION])]) {
+            Ok(Some(mut path)) => {
+                if !is_project(&path) {
+                    path.set_extension(EXTENSION);
+                }
+                match project::project::save(&path, &project) {
+                    Ok(()) => {
+                        self.name_used();
+                        self.toasts.info(format!("Saved {}", path.display()))
+                    }
+                    Err(error) => self.report(error, None),
+                }
+            }
+            Ok(None) => {}
+            Err(error) => self.report(error, None),
+        }
+    }
+
+    // runs the enabled after capture actions, in the order chosen in the options;
+    // returns where the capture has been saved
+    fn after_capture(&mut self, buffer: &[u8], mut saved: Option<PathBuf>) -> Option<PathBuf> {
+        let mut file = saved.clone();
+        let mut message = None;
+        for action in self.pipeline.enabled() {
+            if action.needs_file() && file.is_none() {
+                match temp_file(buffer) {
+                    Ok(path) => file = Some(path),
+                    Err(error) => {
+                        self.report(error, None);
+                        continue;
+                    }
+                }
+            }
+            let result = match action {
+                PostAction::Save => match saved {
+                    Some(_) => Ok(()),
+                    None => self.save_to_default_location(buffer).map(|(path, text)| {
+                        file = Some(path.clone());
+                        saved = Some(path);
+                        message = Some(text);
+                    }),
+                },
+                PostAction::Copy => copy(buffer, CopyFormat::Image, None),
+                PostAction::OpenInEditor => self.pipeline.open_in_editor(file.as_ref().unwrap()),
+                PostAction::RunCommand => self.pipeline.run_command(file.as_ref().unwrap()),
+                PostAction::Notify => {
+                    match (&message, &saved) {
+                        (Some(message), _) => self.toasts.info(message.clone()),
+                        (None, Some(path)) => {
+                            self.toasts.info(format!("Saved {}", path.display()))
+                        }
+                        (None, None) => self.toasts.info("Screenshot captured"),
+                    }
+                    Ok(())
+                }
+            };
+            if let Err(error) = result {
+                self.report(error, Some(buffer));
+            }
+        }
+        saved
+    }
+
+    fn name_context(&self) -> NameContext {
+        NameContext {
+            now: Local::now(),
+            counter: self.name_counter,
+            screen: self.capture_info.screen,
+            mode: self.capture_info.mode.to_string(),
+            width: self.capture_info.width,
+            height: self.capture_info.height,
+            window_title: self.capture_info.window_title.clone(),
+        }
+    }
+
+    // a broken template falls back to the default one
+    fn default_name(&self) -> String {
+        let context = self.name_context();
+        render(&self.name_template, &context)
+            .or_else(|_| render(DEFAULT_TEMPLATE, &context))
+            .unwrap_or_else(|_| "screenshot".to_string())
+    }
+
+    // the counter moves on only once a file has been written with the name
+    fn name_used(&mut self) {
+        self.name_counter += 1;
+    }
+
+    fn default_dir(&self) -> PathBuf {
+        let current = std::env::current_dir().unwrap_or_else(|_| std::env::temp_dir());
+        let mut dir = current.join(&self.default_location);
+        if !dir.exists() {
+            dir = current.join("screenshots");
+        }
+        dir
+    }
+
+    // saves without asking, never overwriting an existing file
+    // returns the path and the message to show
+    fn save_to_default_location(&mut self, buffer: &[u8]) -> Result<(PathBuf, String), AppError> {
+        let dir = self.default_dir();
+        fs::create_dir_all(&dir).map_err(|e| AppError::Save(dir.clone(), e.to_string()))?;
+        let name = self.default_name();
+        let mut path = dir.join(format!("{}.png", name));
+        let mut n = 1;
+        while path.exists() {
+            path = dir.join(format!("{}-{}.png", name, n));
+            n += 1;
+        }
+        let metadata = self.metadata();
+        let message = self.write_png(&path, buffer, &metadata)?;
+        self.name_used();
+        Ok((path, message))
+    }
+
+    fn choose_save_path(
+        &mut self,
+        filters: &[(&str, &[&str])],
+    ) -> Result<Option<PathBuf>, AppError> {
+        let name = self.default_name();
+        let dir = self.default_dir();
+        let mut dialog = FileDialog::new().set_location(&dir).set_filename(&name);
+        for (description, extensions) in filters {
+            dialog = dialog.add_filter(description, extensions);
+        }
+        match dialog.show_save_single_file() {
+            Ok(res) => Ok(res),
+            Err(_) => {
+                let mut dialog = FileDialog::new().set_location("~").set_filename(&name);
+                for (description, extensions) in filters {
+                    dialog = dialog.add_filter(description, extensions);
+                }
+                dialog
+                    .show_save_single_file()
+                    .map_err(|e| AppError::Dialog(e.to_string()))
+            }
+        }
+    }
+
+    // true while the window is reduced to a small control (recording or watching)
+    fn is_compact(&self) -> bool {
+        self.recorder.is_some() || self.watcher.is_some()
+    }
+
+    // shrink the window to a small control so the screen below stays usable,
+    // returns where the control is on the desktop
+    fn shrink_window(&mut self, frame: &mut eframe::Frame) -> egui::Rect {
+        let info = frame.info().window_info;
+        let position = info.position.unwrap_or_default();
+        self.restore_window = Some((position, info.size));
+        let control = egui::Rect::from_min_size(
+            egui::Pos2::new(position.x + info.size.x - 250.0, position.y + 10.0),
+            egui::vec2(240.0, 90.0),
+        );
+        frame.set_maximized(false);
+        frame.set_window_size(control.size());
+        frame.set_window_pos(control.min);
+        self.show_options = false;
+        self.show_regions = false;
+        self.show_history = false;
+        control
+    }
+
+    fn expand_window(&mut self, frame: &mut eframe::Frame) {
+        if let Some((position, size)) = self.restore_window.take() {
+            frame.set_window_pos(position);
+            frame.set_window_size(size);
+            frame.set_maximized(true);
+        }
+    }
+
+    fn selected_rect(&self) -> Option<RectangleCrop> {
+        if self.mode {
+            Some(self.screen_rect)
+        } else {
+            None
+        }
+    }
+
+    fn start_recording(&mut self, frame: &mut eframe::Frame) {
+        let rect = self.selected_rect();
+        let screen = match self.schermi.get_screen() {
+            Ok(screen) => screen,
+            Err(error) => return self.report(error, None),
+        };
+        self.shrink_window(frame);
+        self.recorder = Some(Recorder::start(screen, rect, self.record_fps));
+    }
+
+    fn stop_recording(&mut self, frame: &mut eframe::Frame) {
+        if let Some(recorder) = self.recorder.take() {
+            self.expand_window(frame);
+            match recorder.stop() {
+                Some(recording) if !recording.is_empty() => {
+                    match self.choose_save_path(&[
+                        ("GIF Animation", &["gif"]),
+                        ("Animated PNG", &["png"]),
+                        ("WebM Video", &["webm"]),
+                    ]) {
+                        Ok(Some(path)) => match recording.encode(&path) {
+                            Ok(()) => {
+                                self.name_used();
+                                self.toasts.info(format!("Saved {}", path.display()))
+                            }
+                            Err(error) => self.report(AppError::Recording(error), None),
+                        },
+                        Ok(None) => {}
+                        Err(error) => self.report(error, None),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn use_region(&mut self, region: Region) {
+        match region.rect {
+            Some(rect) => {
+                if region.screen_no == self.schermi.default_screen_no {
+                    self.schermi.screen_no = region.screen_no;
+                    self.screen_rect = rect;
+                    self.mode_radio = SelectionMode::Selection;
+                    self.mode = true;
+                    self.selection_id += 1;
+                }
+            }
+            None => {
+                if region.screen_no < self.schermi.no_screens() {
+                    self.schermi.screen_no = region.screen_no;
+                    self.mode_radio = SelectionMode::Screen;
+                    self.mode = false;
+                }
+            }
+        }
+    }
+
     fn run_action(&mut self, action: Action, ctx: &egui::Context, frame: &mut eframe::Frame) {
         match action {
             Action::SetEntireScreen => {
@@ -178,36 +1261,40 @@ impl MyApp {
                 self.timer.open_timer_form();
             }
             Action::StartTimer => {
-                if self.timer.get_seconds() > 0 {
-                    self.timer.start_timer();
-                } else {
+                if !self.timer.start_timer(ctx) {
                     self.run_action(Action::SettingTimer, ctx, frame)
                 }
             }
             Action::HandleTimer => {
-                let now = Instant::now();
-                if now
-                    .duration_since(self.timer.last_decrement().unwrap())
-                    .as_secs_f32()
-                    >= 1.0
-                {
-                    self.timer.handle_positive_timer();
-                    if self.timer.seconds == 0 {
-                        self.timer.handle_negative_timer();
-                        self.window_hidden = true;
-                        frame.set_visible(false);
-                        self.timer.close_timer_form();
+                let left = self.timer.remaining().as_secs_f32().ceil() as u64;
+                if self.tick_sound && left > 0 && self.last_tick != Some(left) {
+                    play(Sound::Tick, &self.tick_wav);
+                }
+                self.last_tick = Some(left);
+                if self.timer.take_fired() {
+                    self.last_tick = None;
+                    if self.record_after_timer {
+                        self.record_after_timer = false;
+                        self.start_recording(frame);
+                    } else {
+                        self.start_capture(frame);
                     }
-                    self.timer.last_decrement_time = Some(now);
+                } else if self.tick_sound || self.timer.remaining() < Duration::from_secs(60) {
+                    // only the countdown label and the ticks need these repaints, the capture is woken by the timer thread
+                    ctx.request_repaint_after(Duration::from_millis(100));
+                } else {
+                    ctx.request_repaint_after(Duration::from_secs(1));
                 }
-                ctx.request_repaint();
             }
             Action::CancelTimer => {
                 self.timer.cancel_timer();
+                self.record_after_timer = false;
+                self.interval.stop();
             }
             Action::Options => {
                 self.show_options = true;
                 if self.show_options {
+                    let name_context = self.name_context();
                     egui::Window::new("Options")
                         .title_bar(true)
                         .frame(egui::Frame {
@@ -242,6 +1329,30 @@ impl MyApp {
                                         Options::Screen,
                                         "  Change screen  ",
                                     );
+                                    ui.label("|");
+                                    ui.selectable_value(
+                                        &mut self.option,
+                                        Options::Capture,
+                                        "  Capture  ",
+                                    );
+                                    ui.label("|");
+                                    ui.selectable_value(
+                                        &mut self.option,
+                                        Options::Schedules,
+                                        "  Schedules  ",
+                                    );
+                                    ui.label("|");
+                                    ui.selectable_value(
+                                        &mut self.option,
+                                        Options::Timer,
+                                        "  Timer  ",
+                                    );
+                                    ui.label("|");
+                                    ui.selectable_value(
+                                        &mut self.option,
+                                        Options::AfterCapture,
+                                        "  After capture  ",
+                                    );
                                 });
                                 ui.add_space(5.0);
                             });
@@ -312,6 +1423,18 @@ impl MyApp {
                                                         txt,
                                                     );
                                                 }
+                                                for region in self.regions.list().iter() {
+                                                    let txt = format!(
+                                                        "{} ({})",
+                                                        Action::CaptureRegion(region.id).to_string(),
+                                                        region.name
+                                                    );
+                                                    ui.selectable_value(
+                                                        &mut self.new_shortcut.action,
+                                                        Some(Action::CaptureRegion(region.id)),
+                                                        txt,
+                                                    );
+                                                }
                                             });
 
                                         if ui
@@ -324,6 +1447,7 @@ impl MyApp {
                                         {
                                             self.shortcut_set
                                                 .insert_new_shortcut(&mut self.new_shortcut);
+                                            self.shortcut_set.name_regions(self.regions.list());
                                         }
 
                                         if let Some(_) = self.new_shortcut.key {
@@ -381,11 +1505,11 @@ impl MyApp {
                                         let set_path_text =
                                             ui.text_edit_singleline(&mut self.default_location);
                                         if ui.button("Change").clicked() {
-                                            let result =
-                                                FileDialog::new().show_open_single_dir().unwrap();
-                                            if result.is_some() {
+                                            if let Ok(Some(result)) =
+                                                FileDialog::new().show_open_single_dir()
+                                            {
                                                 self.default_location =
-                                                    result.unwrap().to_string_lossy().to_string();
+                                                    result.to_string_lossy().to_string();
                                             }
                                         }
                                         if set_path_text.changed() {
@@ -401,15 +1525,78 @@ impl MyApp {
                                                 .color(Color32::BLACK),
                                         );
                                         if ui.button("Set default name").clicked() {
-                                            self.default_name_sel = true;
-                                            self.default_name = compute_default_name();
+                                            self.name_template = DEFAULT_TEMPLATE.to_string();
                                         }
                                     });
                                     ui.horizontal(|ui| {
-                                        ui.text_edit_singleline(&mut self.default_name);
-                                        if ui.button("  Save  ").clicked() {
-                                            self.default_name_sel = false;
-                                        }
+                                        ui.add(
+                                            egui::TextEdit::singleline(&mut self.name_template)
+                                                .desired_width(300.0),
+                                        );
+                                        ui.label("Counter:");
+                                        ui.add(egui::DragValue::new(&mut self.name_counter));
+                                    });
+                                    match render(&self.name_template, &name_context) {
+                                        Ok(name) => ui.label(format!("Preview: {}.png", name)),
+                                        Err(error) => ui.label(
+                                            RichText::new(error).color(Color32::DARK_RED),
+                                        ),
+                                    };
+                                    ui.add_space(5.0);
+                                    ui.label(
+                                        "Tokens: {date}, {date:%Y-%m-%d}, {time}, {time:%H%M}, {counter}, {counter:04}, {screen}, {mode}, {width}, {height}, {window_title}",
+                                    );
+                                    ui.add_space(10.0);
+                                    ui.heading("PNG compression");
+                                    ui.add_space(10.0);
+                                    ui.horizontal(|ui| {
+                                        ui.label("Optimization:");
+                                        egui::ComboBox::from_id_source("png_optimization")
+                                            .selected_text(self.png_optimization.to_string())
+                                            .show_ui(ui, |ui| {
+                                                for level in Optimization::all() {
+                                                    ui.selectable_value(
+                                                        &mut self.png_optimization,
+                                                        level,
+                                                        level.to_string(),
+                                                    );
+                                                }
+                                            });
+                                    });
+                                    ui.label(
+                                        "Lossless: fewer colours are stored as a palette and the metadata is dropped",
+                                    );
+                                    ui.add_space(10.0);
+                                    ui.heading("Metadata");
+                                    ui.add_space(10.0);
+                                    ui.checkbox(
+                                        &mut self.embed_metadata,
+                                        "Embed capture time, screen, mode and version in PNG and JPEG files",
+                                    );
+                                    ui.add_enabled_ui(self.embed_metadata, |ui| {
+                                        ui.checkbox(
+                                            &mut self.embed_hostname,
+                                            "Include the computer name",
+                                        );
+                                        egui::Grid::new("metadata_grid")
+                                            .num_columns(2)
+                                            .show(ui, |ui| {
+                                                ui.label("Description:");
+                                                ui.add(
+                                                    egui::TextEdit::singleline(
+                                                        &mut self.description,
+                                                    )
+                                                    .desired_width(300.0),
+                                                );
+                                                ui.end_row();
+                                                ui.label("Tags:");
+                                                ui.add(
+                                                    egui::TextEdit::singleline(&mut self.tags)
+                                                        .desired_width(300.0)
+                                                        .hint_text("separated by commas"),
+                                                );
+                                                ui.end_row();
+                                            });
                                     });
                                 }
                                 Options::Screen => {
@@ -433,14 +1620,211 @@ impl MyApp {
                                         self.mode = false;
                                     }
                                 }
+                                Options::Capture => {
+                                    ui.add_space(10.0);
+                                    ui.heading("Mouse cursor");
+                                    ui.add_space(10.0);
+                                    ui.checkbox(
+                                        &mut self.include_cursor,
+                                        "Include the mouse cursor in the screenshot",
+                                    );
+                                    ui.add_enabled(
+                                        self.include_cursor,
+                                        egui::Checkbox::new(
+                                            &mut self.cursor_halo,
+                                            "Highlight the cursor with a halo",
+                                        ),
+                                    );
+                                    if cfg!(not(target_os = "linux")) {
+                                        ui.label(
+                                            RichText::new("Available only on Linux (X11)")
+                                                .color(Color32::DARK_RED),
+                                        );
+                                    }
+                                    ui.add_space(10.0);
+                                    ui.heading("Recording");
+                                    ui.add_space(10.0);
+                                    ui.horizontal(|ui| {
+                                        ui.label("Frames per second:");
+                                        ui.add(egui::Slider::new(&mut self.record_fps, 1..=30));
+                                    });
+                                    ui.add_space(10.0);
+                                    ui.heading("Watch for changes");
+                                    ui.add_space(10.0);
+                                    egui::Grid::new("watch_grid")
+                                        .num_columns(2)
+                                        .spacing([40.0, 4.0])
+                                        .show(ui, |ui| {
+                                            ui.label("Checks per second:");
+                                            ui.add(egui::Slider::new(&mut self.watch_rate, 1..=10));
+                                            ui.end_row();
+                                            ui.label("Changed pixels to trigger:");
+                                            ui.add(
+                                                egui::Slider::new(
+                                                    &mut self.watch_threshold,
+                                                    0.1..=50.0,
+                                                )
+                                                .suffix("%"),
+                                            );
+                                            ui.end_row();
+                                            ui.label("Pause after a capture:");
+                                            ui.add(
+                                                egui::Slider::new(&mut self.watch_cooldown, 1..=300)
+                                                    .suffix(" s"),
+                                            );
+                                            ui.end_row();
+                                        });
+                                }
+                                Options::Schedules => {
+                                    ui.add_space(10.0);
+                                    ui.heading("Scheduled captures");
+                                    ui.add_space(10.0);
+                                    let mut delete_index = None;
+                                    egui::Grid::new("schedules_grid")
+                                        .num_columns(3)
+                                        .spacing([40.0, 4.0])
+                                        .striped(true)
+                                        .show(ui, |ui| {
+                                            for (i, schedule) in
+                                                self.schedules.list().iter().enumerate()
+                                            {
+                                                ui.label(schedule.expression());
+                                                match schedule.next() {
+                                                    Some(next) => ui.label(format!(
+                                                        "next: {}",
+                                                        next.format("%a %Y-%m-%d %H:%M")
+                                                    )),
+                                                    None => ui.label(""),
+                                                };
+                                                if ui.button("  🗑  ").clicked() {
+                                                    delete_index = Some(i);
+                                                }
+                                                ui.end_row();
+                                            }
+                                        });
+                                    if let Some(i) = delete_index {
+                                        self.schedules.remove(i);
+                                    }
+                                    ui.add_space(10.0);
+                                    ui.horizontal(|ui| {
+                                        ui.add(
+                                            egui::TextEdit::singleline(
+                                                &mut self.schedules.new_expression,
+                                            )
+                                            .hint_text("every weekday at 09:00"),
+                                        );
+                                        if ui.button("  +  ").on_hover_text("Add schedule").clicked()
+                                        {
+                                            self.schedules.add();
+                                        }
+                                    });
+                                    if let Some(error) = &self.schedules.error {
+                                        ui.label(
+                                            RichText::new(error.as_str()).color(Color32::DARK_RED),
+                                        );
+                                    }
+                                    ui.label(
+                                        "Examples: \"at 17:30 today\", \"every day at 12:00\", \"*/15 9-18 * * 1-5\" (cron)",
+                                    );
+                                }
+                                Options::Timer => {
+                                    ui.add_space(10.0);
+                                    ui.heading("Countdown");
+                                    ui.add_space(10.0);
+                                    ui.horizontal(|ui| {
+                                        ui.label("Position on the screen:");
+                                        egui::ComboBox::from_id_source("countdown_position")
+                                            .selected_text(self.countdown_position.to_string())
+                                            .show_ui(ui, |ui| {
+                                                for position in CountdownPosition::all() {
+                                                    ui.selectable_value(
+                                                        &mut self.countdown_position,
+                                                        position,
+                                                        position.to_string(),
+                                                    );
+                                                }
+                                            });
+                                    });
+                                    ui.add_space(10.0);
+                                    ui.heading("Feedback");
+                                    ui.add_space(10.0);
+                                    ui.checkbox(&mut self.tick_sound, "Tick every second");
+                                    wav_picker(ui, &mut self.tick_wav, Sound::Tick);
+                                    ui.checkbox(&mut self.shutter_sound, "Shutter sound on capture");
+                                    wav_picker(ui, &mut self.shutter_wav, Sound::Shutter);
+                                    ui.checkbox(&mut self.flash, "Flash the screen on capture");
+                                    ui.label("Leave the file empty to use the built-in sounds");
+                                }
+                                Options::AfterCapture => {
+                                    ui.add_space(10.0);
+                                    ui.heading("After every capture");
+                                    ui.add_space(10.0);
+                                    let mut move_up = None;
+                                    let mut move_down = None;
+                                    let count = self.pipeline.steps().len();
+                                    egui::Grid::new("after_capture_grid")
+                                        .num_columns(3)
+                                        .spacing([20.0, 4.0])
+                                        .striped(true)
+                                        .show(ui, |ui| {
+                                            for (i, step) in
+                                                self.pipeline.steps_mut().iter_mut().enumerate()
+                                            {
+                                                ui.label(format!("{}.", i + 1));
+                                                ui.checkbox(
+                                                    &mut step.enabled,
+                                                    step.action.to_string(),
+                                                );
+                                                ui.horizontal(|ui| {
+                                                    if ui
+                                                        .add_enabled(i > 0, egui::Button::new("⏶"))
+                                                        .clicked()
+                                                    {
+                                                        move_up = Some(i);
+                                                    }
+                                                    if ui
+                                                        .add_enabled(
+                                                            i + 1 < count,
+                                                            egui::Button::new("⏷"),
+                                                        )
+                                                        .clicked()
+                                                    {
+                                                        move_down = Some(i);
+                                                    }
+                                                });
+                                                ui.end_row();
+                                            }
+                                        });
+                                    if let Some(i) = move_up {
+                                        self.pipeline.move_up(i);
+                                    }
+                                    if let Some(i) = move_down {
+                                        self.pipeline.move_down(i);
+                                    }
+                                    ui.add_space(10.0);
+                                    ui.horizontal(|ui| {
+                                        ui.label("Editor:");
+                                        ui.add(
+                                            egui::TextEdit::singleline(&mut self.pipeline.editor)
+                                                .hint_text("default application"),
+                                        );
+                                    });
+                                    ui.horizontal(|ui| {
+                                        ui.label("Shell command:");
+                                        ui.add(
+                                            egui::TextEdit::singleline(&mut self.pipeline.command)
+                                                .hint_text("cp {path} ~/Pictures"),
+                                        );
+                                    });
+                                    ui.label("{path} is replaced by the file of the capture");
+                                }
                             }
                         });
                 }
             }
             Action::Capture => {
                 self.timer.cancel_timer();
-                self.window_hidden = true;
-                frame.set_visible(false);
+                self.start_capture(frame);
             }
             Action::Close => {
                 frame.close();
@@ -455,6 +1839,7 @@ impl MyApp {
                 self.annotation_element.arrow.clear();
                 self.annotation_element.line.clear();
                 self.annotation_element.circle.clear();
+                self.annotation_element.magnifier.clear();
                 self.last_modify.clear();
                 self.selection_annotation = SelectionAnnotation::NotSelected;
                 self.annotation = false;
@@ -463,62 +1848,53 @@ impl MyApp {
                 self.show_options = false;
                 self.mode = false;
                 frame.set_visible(false);
-                self.mac_bug = true;
-                self.get_real_monitor = 6;
+                self.capture.reshow();
             }
             Action::Save => {
-                let mut name = compute_default_name();
-                if !self.default_name_sel {
-                    if self.default_name_num != 0 {
-                        name = format!("{}-{}", self.default_name, self.default_name_num);
-                        self.default_name_num += 1;
-                    } else {
-                        name = self.default_name.clone();
-                        self.default_name_num += 1;
-                    }
-                }
-                let mut dir: std::path::PathBuf = std::env::current_dir().unwrap();
-                dir.push(&self.default_location);
-                if !dir.exists() {
-                    dir = std::env::current_dir().unwrap();
-                    dir.push("screenshots");
-                }
-                let result = match FileDialog::new()
-                    .set_location(&dir)
-                    .set_filename(&name)
-                    .add_filter("PNG Image", &["png"])
-                    .add_filter("JPEG Image", &["jpg", "jpeg"])
-                    .add_filter("GIF Image", &["gif"])
-                    .show_save_single_file()
-                {
-                    Ok(res) => res,
-                    Err(_) => FileDialog::new()
-                        .set_location("~")
-                        .set_filename(&name)
-                        .add_filter("PNG Image", &["png"])
-                        .add_filter("JPEG Image", &["jpg", "jpeg"])
-                        .add_filter("GIF Image", &["gif"])
-                        .show_save_single_file()
-                        .unwrap(),
+                let buffer = match self.buffer.clone() {
+                    Some(buffer) => buffer,
+                    None => return,
                 };
+                let result = self.choose_save_path(&[
+                    ("PNG Image", &["png"]),
+                    ("JPEG Image", &["jpg", "jpeg"]),
+                    ("GIF Image", &["gif"]),
+                    ("SVG Image", &["svg"]),
+                    ("PDF Document", &["pdf"]),
+                ]);
                 match result {
-                    Some(result) => {
-                        fs::write(result.clone(), self.buffer.clone().unwrap()).unwrap();
-                    }
-                    None => {}
+                    Ok(Some(result)) => match self.write_image(&result, &buffer, frame) {
+                        Ok(message) => {
+                            if !matches!(
+                                result.extension().and_then(|e| e.to_str()),
+                                Some("svg") | Some("pdf")
+                            ) {
+                                self.saved_path = Some(result.clone());
+                            }
+                            self.name_used();
+                            self.toasts.info(message)
+                        }
+                        Err(error) => self.report(error, Some(&buffer)),
+                    },
+                    Ok(None) => {}
+                    Err(error) => self.report(error, Some(&buffer)),
                 };
             }
-            Action::Copy => {
-                let mut ctx_clip = Clipboard::new().unwrap();
-                let image = load_image_from_memory(&self.buffer.clone().unwrap()).unwrap();
-                let bytes = image.as_raw();
-
-                let img_data = ImageData {
-                    width: image.width() as usize,
-                    height: image.height() as usize,
-                    bytes: Cow::from(bytes.as_ref()),
+            Action::QuickSave => {
+                let buffer = match self.buffer.clone() {
+                    Some(buffer) => buffer,
+                    None => return,
                 };
-                ctx_clip.set_image(img_data).unwrap();
+                match self.save_to_default_location(&buffer) {
+                    Ok((path, message)) => {
+                        self.toasts.info(message);
+                        self.saved_path = Some(path);
+                    }
+                    Err(error) => self.report(error, Some(&buffer)),
+                }
+            }
+            Action::Copy => {
+                self.copy_as(CopyFormat::Image, frame);
             }
             Action::Undo => {
                 if let Some(last) = self.last_modify.pop() {
@@ -552,60 +1928,246 @@ impl MyApp {
                         SelectionAnnotation::Text => {
                             self.annotation_element.text.pop();
                         }
+                        SelectionAnnotation::Magnifier => {
+                            self.annotation_element.magnifier.pop();
+                        }
                         SelectionAnnotation::Crop => {}
+                        SelectionAnnotation::Adjust => self.undo_adjustment(ctx),
+                    }
+                }
+            }
+            Action::RepeatLastCapture => {
+                if let Some(region) = self.regions.get_last() {
+                    self.capture_region(region, frame);
+                }
+            }
+            Action::CaptureRegion(id) => {
+                if let Some(region) = self.regions.get(id) {
+                    self.capture_region(region, frame);
+                }
+            }
+            Action::Record => {
+                if self.recorder.is_some() {
+                    self.stop_recording(frame);
+                } else if !self.timer.is_timer_running() {
+                    if self.timer.start_timer(ctx) {
+                        self.record_after_timer = true;
+                    } else {
+                        self.start_recording(frame);
                     }
                 }
             }
+            Action::Watch => {
+                if let Some(watcher) = self.watcher.take() {
+                    watcher.stop();
+                    self.expand_window(frame);
+                } else if self.recorder.is_none() {
+                    let rect = self.selected_rect();
+                    let screen = match self.schermi.get_screen() {
+                        Ok(screen) => screen,
+                        Err(error) => return self.report(error, None),
+                    };
+                    let control = self.shrink_window(frame);
+                    self.watcher = Some(Watcher::start(
+                        ctx,
+                        screen,
+                        rect,
+                        control,
+                        self.watch_rate,
+                        self.watch_threshold,
+                        Duration::from_secs(self.watch_cooldown as u64),
+                    ));
+                }
+            }
+            Action::OpenImage => {
+                let dialog = FileDialog::new()
+                    .add_filter(
+                        "Images and projects",
+                        &[
+                            "png", "jpg", "jpeg", "gif", "bmp", "webp", "tif", "tiff", "ico",
+                            EXTENSION,
+                        ],
+                    )
+                    .show_open_single_file();
+                match dialog {
+                    Ok(Some(path)) => self.open_file(&path, ctx, frame),
+                    Ok(None) => {}
+                    Err(error) => self.report(AppError::Dialog(error.to_string()), None),
+                }
+            }
+            Action::Paste => match paste() {
+                Ok(Pasted::Image(buffer)) => self.load_image(&buffer, "clipboard", ctx),
+                Ok(Pasted::File(path)) => self.open_file(&path, ctx, frame),
+                Err(error) => self.report(error, None),
+            },
+            Action::ScrollCapture => {
+                if self.mode {
+                    self.scrolling = true;
+                    self.run_action(Action::Capture, ctx, frame);
+                }
+            }
         }
     }
 }
 impl eframe::App for MyApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let config = Config {
+            regions: self.regions.list().clone(),
+            region_shortcuts: self.shortcut_set.region_shortcuts(),
+            include_cursor: self.include_cursor,
+            cursor_halo: self.cursor_halo,
+            record_fps: self.record_fps,
+            schedules: self.schedules.expressions(),
+            watch_rate: self.watch_rate,
+            watch_threshold: self.watch_threshold,
+            watch_cooldown: self.watch_cooldown,
+            tick_sound: self.tick_sound,
+            tick_wav: self.tick_wav.clone(),
+            shutter_sound: self.shutter_sound,
+            shutter_wav: self.shutter_wav.clone(),
+            flash: self.flash,
+            countdown_position: self.countdown_position,
+            after_capture: self.pipeline.steps().clone(),
+            editor: self.pipeline.editor.clone(),
+            command: self.pipeline.command.clone(),
+            name_template: self.name_template.clone(),
+            name_counter: self.name_counter,
+            history_count: self.history.max_count,
+            history_days: self.history.max_days,
+            adjust: self.adjust_settings,
+            framing: self.framing.clone(),
+            png_optimization: self.png_optimization,
+            embed_metadata: self.embed_metadata,
+            embed_hostname: self.embed_hostname,
+            description: self.description.clone(),
+            tags: self.tags.clone(),
+        };
+        config.store(storage);
+    }
+
+    fn persist_native_window(&self) -> bool {
+        false
+    }
+
+    fn persist_egui_memory(&self) -> bool {
+        false
+    }
+
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         ctx.set_visuals(egui::Visuals::light());
-        if self.mac_bug {
-            std::thread::sleep(Duration::from_millis(100));
-            frame.set_visible(true);
-            self.mac_bug = false;
-        }
-        if self.window_hidden {
-            std::thread::sleep(Duration::from_millis(300));
-            let mut screen = self.schermi.get_screen();
-            let image;
-            if self.annotation {
-                screen = self.schermi.get_default_screen();
-            }
-            if self.mode || self.annotation {
-                image = screen
-                    .capture_area(
-                        self.screen_rect.x_left.floor() as i32,
-                        self.screen_rect.y_left.floor() as i32,
-                        self.screen_rect.width.floor() as u32,
-                        self.screen_rect.height.floor() as u32,
-                    )
-                    .unwrap();
+        for result in self.png_writer.finished() {
+            match result {
+                Ok(Some(message)) => self.toasts.info(message),
+                Ok(None) => {}
+                Err(error) => self.report(error, None),
+            }
+        }
+        if self.png_writer.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        match self.capture.step() {
+            Step::Idle => {}
+            Step::Wait(wait) => ctx.request_repaint_after(wait),
+            Step::Capture => match self.capture_request() {
+                Ok(request) => self.capture.run(ctx, request),
+                Err(error) => {
+                    self.capture.reshow();
+                    self.auto_save_capture = false;
+                    // a failed shot still counts, so the interval can finish
+                    self.interval.check_finished();
+                    self.report(error, None);
+                }
+            },
+            Step::Done(Ok(buffer)) => {
+                self.captured(buffer, ctx);
+                ctx.request_repaint();
+            }
+            Step::Done(Err(error)) => {
+                self.auto_save_capture = false;
+                self.interval.check_finished();
+                self.report(error, None);
+                ctx.request_repaint();
+            }
+            Step::Show => {
+                frame.set_visible(true);
+                if self.flash_start.is_some() {
+                    // the flash starts when the window is back on the screen
+                    self.flash_start = Some(Instant::now());
+                }
+            }
+        }
+        if let Some(start) = self.flash_start {
+            let t = start.elapsed().as_secs_f32() / 0.4;
+            if t < 1.0 {
+                let alpha = ((1.0 - t) * 200.0) as u8;
+                ctx.layer_painter(egui::LayerId::new(
+                    egui::Order::Foreground,
+                    egui::Id::new("flash"),
+                ))
+                .rect_filled(
+                    ctx.screen_rect(),
+                    egui::Rounding::none(),
+                    Color32::from_white_alpha(alpha),
+                );
+                ctx.request_repaint();
             } else {
-                image = screen.capture().unwrap();
+                self.flash_start = None;
             }
-            self.buffer = Some(image.to_png(None).unwrap());
-            self.texture = Some(ctx.load_texture(
-                "my-image",
-                load_image_from_memory(&self.buffer.clone().unwrap()).unwrap(),
-                Default::default(),
-            ));
-            self.window_hidden = false;
-            self.image_viewer = true;
-            self.mode = false;
-            self.annotation = false;
-            self.show_options = false;
-            self.annotation_element.pen.clear();
-            self.annotation_element.rect.clear();
-            self.annotation_element.text.clear();
-            self.annotation_element.arrow.clear();
-            self.annotation_element.line.clear();
-            self.annotation_element.circle.clear();
-            self.last_modify.clear();
-            frame.set_visible(false);
-            self.mac_bug = true;
+        }
+        self.toasts.show(ctx);
+        // image files dropped on the window are opened in the editor
+        let (hovered, dropped) = ctx.input(|i| {
+            (
+                !i.raw.hovered_files.is_empty(),
+                i.raw.dropped_files.iter().find_map(|f| f.path.clone()),
+            )
+        });
+        if self.capture.is_idle() && !self.is_compact() {
+            if hovered {
+                let painter =
+                    ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop")));
+                painter.rect_filled(
+                    ctx.screen_rect(),
+                    egui::Rounding::none(),
+                    Color32::from_black_alpha(120),
+                );
+                painter.text(
+                    ctx.screen_rect().center(),
+                    egui::Align2::CENTER_CENTER,
+                    "Drop to open the image",
+                    egui::FontId::proportional(30.0),
+                    Color32::WHITE,
+                );
+            }
+            if let Some(path) = dropped {
+                self.open_file(&path, ctx, frame);
+            }
+        }
+        let now = Local::now();
+        // while the editor is open a capture would take its selection and skip saving,
+        // so due schedules wait for it to close
+        if !self.annotation {
+            if self.capture.is_idle() && self.schedules.take_due(now) {
+                self.auto_save_capture = true;
+                self.start_capture(frame);
+                ctx.request_repaint();
+            }
+            if let Some(next) = self.schedules.next_due() {
+                // wall clock times are checked again at least every 30 seconds
+                let wait = (next - now).to_std().unwrap_or_default();
+                ctx.request_repaint_after(wait.min(Duration::from_secs(30)));
+            }
+        }
+        // take_fired only when a shot can start, so none is consumed during a capture
+        if self.capture.is_idle() && self.interval.take_fired() {
+            self.auto_save_capture = true;
+            self.start_capture(frame);
+            ctx.request_repaint();
+        }
+        if self.capture.is_idle() && self.watcher.as_mut().is_some_and(|w| w.take_triggered()) {
+            self.auto_save_capture = true;
+            self.start_capture(frame);
+            ctx.request_repaint();
         }
         let position_bar_x = frame.info().window_info.size.x / 2.0;
         let position_bar_y = frame.info().window_info.size.y - 70.0;
@@ -616,7 +2178,9 @@ impl eframe::App for MyApp {
                 egui::Pos2::new(position_bar_x, position_bar_y),
                 egui::Vec2::new(316.0, 30.0),
             ))
-            .open(&mut (self.get_real_monitor == 15 && !self.image_viewer))
+            .open(&mut (self.monitor_detected
+                && !self.image_viewer
+                && !self.is_compact()))
             .frame(egui::Frame {
                 fill: egui::Color32::GRAY,
                 stroke: egui::Stroke::new(0.5, egui::Color32::BLACK),
@@ -678,10 +2242,82 @@ impl eframe::App for MyApp {
                             {
                                 self.run_action(Action::SettingTimer, ctx, frame);
                             }
+                            if ui
+                                .button(" ⟳ ")
+                                .on_hover_text("Capture at regular intervals")
+                                .clicked()
+                            {
+                                self.interval.toggle_form();
+                            }
+                            if self.interval.is_form_open() {
+                                ui.label("Every:");
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.interval.every_text)
+                                        .hint_text("10s")
+                                        .desired_width(50.0),
+                                );
+                                ui.label("Shots:");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.interval.shots)
+                                        .clamp_range(0..=10000)
+                                        .custom_formatter(|n, _| {
+                                            if n == 0.0 {
+                                                "∞".to_string()
+                                            } else {
+                                                format!("{}", n)
+                                            }
+                                        }),
+                                )
+                                .on_hover_text("0 captures until stopped");
+                                if ui.button("Start").clicked() {
+                                    self.interval.start(ctx);
+                                }
+                            }
+                            if ui
+                                .button(" 📌 ")
+                                .on_hover_text("Saved regions")
+                                .clicked()
+                            {
+                                self.show_regions = !self.show_regions;
+                            }
+                            if ui
+                                .button(" 🕘 ")
+                                .on_hover_text("History of the captures")
+                                .clicked()
+                            {
+                                self.show_history = !self.show_history;
+                            }
+                            if ui
+                                .button(" 📂 ")
+                                .on_hover_text("Open an image or a project")
+                                .clicked()
+                            {
+                                self.run_action(Action::OpenImage, ctx, frame);
+                            }
+                            if ui
+                                .button(" 📥 ")
+                                .on_hover_text("Paste an image from the clipboard")
+                                .clicked()
+                            {
+                                self.run_action(Action::Paste, ctx, frame);
+                            }
 
                             if self.timer.is_timer_form_open() {
-                                ui.label("Timer (seconds):");
-                                ui.add(egui::Slider::new(&mut self.timer.seconds, 0..=60));
+                                ui.label("Timer:");
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.timer.text)
+                                        .hint_text("1m30s")
+                                        .desired_width(60.0),
+                                )
+                                .on_hover_text("Seconds, or a duration like 1h, 1m30s, 2.5s");
+                                if !self.timer.text.trim().is_empty() {
+                                    match self.timer.get_delay() {
+                                        Some(delay) => ui.label(format_duration(delay)),
+                                        None => ui.label(
+                                            RichText::new("invalid").color(Color32::DARK_RED),
+                                        ),
+                                    };
+                                }
 
                                 if ui.button("Start Timer").clicked() {
                                     self.run_action(Action::StartTimer, ctx, frame)
@@ -708,6 +2344,29 @@ impl eframe::App for MyApp {
                             if ui.button("  Capture  ").clicked() {
                                 self.run_action(Action::Capture, ctx, frame)
                             }
+                            if ui
+                                .button("  ⏺  ")
+                                .on_hover_text("Record the screen (uses the timer, if set)")
+                                .clicked()
+                            {
+                                self.run_action(Action::Record, ctx, frame)
+                            }
+                            if ui
+                                .button("  👁  ")
+                                .on_hover_text("Capture automatically when the screen changes")
+                                .clicked()
+                            {
+                                self.run_action(Action::Watch, ctx, frame)
+                            }
+                            if cfg!(feature = "scroll")
+                                && self.mode
+                                && ui
+                                    .button("  ⇣  ")
+                                    .on_hover_text("Scrolling capture of the selection")
+                                    .clicked()
+                            {
+                                self.run_action(Action::ScrollCapture, ctx, frame)
+                            }
                             if ui
                                 .add(
                                     egui::Button::new("  X  ").rounding(egui::Rounding::same(50.0)),
@@ -763,12 +2422,22 @@ impl eframe::App for MyApp {
                             if ui.button("  📋  ").on_hover_text("Copy").clicked() {
                                 self.run_action(Action::Copy, ctx, frame);
                             }
+                            if let Some(format) = copy_menu(ui, " ⏷ ") {
+                                self.copy_as(format, frame);
+                            }
                             if ui.button("  Take another Screenshot  ").clicked() {
                                 self.run_action(Action::TakeAnotherScreenshot, ctx, frame)
                             }
                             if ui.button("  Save  ").clicked() {
                                 self.run_action(Action::Save, ctx, frame)
                             }
+                            if ui
+                                .button("  ⚡  ")
+                                .on_hover_text("Quick save to the default location")
+                                .clicked()
+                            {
+                                self.run_action(Action::QuickSave, ctx, frame)
+                            }
 
                             if ui
                                 .add(
@@ -810,6 +2479,24 @@ impl eframe::App for MyApp {
                                 "  ⭕  ",
                             )
                             .on_hover_text("Draw a circle");
+                            ui.selectable_value(
+                                &mut self.selection_annotation,
+                                SelectionAnnotation::Magnifier,
+                                "  🔍  ",
+                            )
+                            .on_hover_text("Magnify a detail: select it, then click where the copy goes");
+                            if self.selection_annotation == SelectionAnnotation::Magnifier {
+                                let element = &mut self.annotation_element;
+                                ui.selectable_value(&mut element.magnifier_circle, true, " ⭕ ");
+                                ui.selectable_value(&mut element.magnifier_circle, false, " ☐ ");
+                                ui.add(
+                                    egui::DragValue::new(&mut element.magnifier_zoom)
+                                        .clamp_range(1.5..=10.0)
+                                        .speed(0.1)
+                                        .max_decimals(1)
+                                        .suffix("×"),
+                                );
+                            }
                             ui.selectable_value(
                                 &mut self.selection_annotation,
                                 SelectionAnnotation::Text,
@@ -847,9 +2534,19 @@ impl eframe::App for MyApp {
                             if self.selection_annotation == SelectionAnnotation::Crop {
                                 if ui.button("  Save crop  ").clicked() {
                                     self.selection_annotation = SelectionAnnotation::NotSelected;
-                                    self.window_hidden = true;
+                                    self.capture.start(false);
                                 }
                             }
+                            if let Some(adjustment) = adjust_menu(ui, &mut self.adjust_settings) {
+                                self.adjust_image(|b| apply(b, &[adjustment]), ctx, frame);
+                            }
+                            if ui
+                                .selectable_label(self.show_framing, "  🖼  ")
+                                .on_hover_text("Frame on a background")
+                                .clicked()
+                            {
+                                self.show_framing = !self.show_framing;
+                            }
                             ui.label("|");
                             egui::stroke_ui(ui, &mut self.annotation_element.stroke, "Stroke");
                             ui.label("|");
@@ -863,11 +2560,46 @@ impl eframe::App for MyApp {
                                 self.annotation_element.arrow.clear();
                                 self.annotation_element.line.clear();
                                 self.annotation_element.circle.clear();
+                                self.annotation_element.magnifier.clear();
                                 self.last_modify.clear();
                                 self.selection_annotation = SelectionAnnotation::NotSelected;
                                 self.annotation = false;
+                                // back to the image before the adjustments
+                                let original = self.adjusted.drain(..).next();
+                                if let Some(original) = original {
+                                    if let Err(error) = self.replace_image(original.buffer, ctx) {
+                                        self.report(error, None);
+                                    }
+                                }
+                            }
+                            if let Some(format) = copy_menu(ui, "  📋  ") {
+                                self.copy_as(format, frame);
+                            }
+                            if ui
+                                .button("  💼  ")
+                                .on_hover_text("Save as a project, the annotations stay editable")
+                                .clicked()
+                            {
+                                self.save_project(frame);
+                            }
+                            if ui
+                                .button("  ⬇  ")
+                                .on_hover_text("Export as SVG or PDF, the annotations stay vectors")
+                                .clicked()
+                            {
+                                self.export_vector(frame);
                             }
-                            if ui.button("  Save modify  ").clicked() {
+                            let save_modify = ui.button("  Save modify  ").clicked();
+                            if save_modify && !self.adjusted.is_empty() {
+                                // a capture of the viewer would lose the resolution and the
+                                // transparency of the adjusted image
+                                match self.current_image(frame) {
+                                    Ok(Some(buffer)) => self.show_image(buffer, ctx),
+                                    Ok(None) => {}
+                                    Err(error) => self.report(error, None),
+                                }
+                                self.selection_annotation = SelectionAnnotation::NotSelected;
+                            } else if save_modify {
                                 let dim_image = resize_image_to_fit_container(
                                     frame.info().window_info.size.x / 3.0 * 2.0,
                                     frame.info().window_info.size.y / 3.0 * 2.0,
@@ -899,16 +2631,102 @@ impl eframe::App for MyApp {
                                     height: dim_image.1 * adj,
                                 };
                                 self.selection_annotation = SelectionAnnotation::NotSelected;
-                                self.window_hidden = true;
+                                self.capture.start(false);
                             }
                         }
                     },
                 );
             });
 
+        let (adj, mc_adj) = screen_adjustment(frame);
+        if self.mode && !self.image_viewer && !self.is_compact() {
+            let before = (self.selection_preset, self.screen_rect);
+            egui::Window::new("Selection")
+                .title_bar(false)
+                .anchor(egui::Align2::CENTER_TOP, [0.0, 10.0])
+                .frame(egui::Frame {
+                    fill: egui::Color32::GRAY,
+                    stroke: egui::Stroke::new(0.5, egui::Color32::BLACK),
+                    inner_margin: egui::style::Margin::same(15.0),
+                    rounding: egui::Rounding::same(20.0),
+                    ..Default::default()
+                })
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_source("Selection presets")
+                            .selected_text(self.selection_preset.to_string())
+                            .show_ui(ui, |ui| {
+                                for p in SelectionPreset::all() {
+                                    ui.selectable_value(
+                                        &mut self.selection_preset,
+                                        p,
+                                        p.to_string(),
+                                    );
+                                }
+                            });
+                        let max_x = frame.info().window_info.size[0] * adj;
+                        let max_y = frame.info().window_info.size[1] * adj;
+                        ui.label("x:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.screen_rect.x_left)
+                                .clamp_range(0.0..=max_x)
+                                .max_decimals(0),
+                        );
+                        ui.label("y:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.screen_rect.y_left)
+                                .clamp_range(0.0..=max_y)
+                                .max_decimals(0),
+                        );
+                        ui.label("width:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.screen_rect.width)
+                                .clamp_range(1.0..=max_x)
+                                .max_decimals(0),
+                        );
+                        ui.label("height:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.screen_rect.height)
+                                .clamp_range(1.0..=max_y)
+                                .max_decimals(0),
+                        );
+                    });
+                });
+            if before != (self.selection_preset, self.screen_rect) {
+                let by_height = self.screen_rect.width == before.1.width
+                    && self.screen_rect.height != before.1.height;
+                let (width, height) = self.selection_preset.constrain(
+                    self.screen_rect.width,
+                    self.screen_rect.height,
+                    by_height,
+                );
+                self.screen_rect.width = width;
+                self.screen_rect.height = height;
+                self.selection_id += 1;
+            }
+        }
+        let default_selection = if self.screen_rect.width > 0.0 && self.screen_rect.height > 0.0 {
+            egui::Rect::from_min_size(
+                egui::Pos2::new(
+                    self.screen_rect.x_left / adj,
+                    self.screen_rect.y_left / adj - mc_adj,
+                ),
+                egui::vec2(self.screen_rect.width / adj, self.screen_rect.height / adj),
+            )
+        } else {
+            egui::Rect::from_min_size(
+                egui::Pos2::new(
+                    (frame.info().window_info.size[0] - 320.0) / 2.0,
+                    (frame.info().window_info.size[1] - 240.0) / 2.0,
+                ),
+                egui::vec2(320.0, 240.0),
+            )
+        };
         let w = egui::Window::new("resize")
+            .id(egui::Id::new(("resize", self.selection_id)))
             .title_bar(false)
-            .default_size(egui::vec2(320.0, 240.0))
+            .default_size(default_selection.size())
             .resizable(true)
             .movable(true)
             .resize(|r| {
@@ -918,11 +2736,8 @@ impl eframe::App for MyApp {
                 ))
             })
             .resize(|r| r.min_size(egui::vec2(1.0, 1.0)))
-            .default_pos(egui::Pos2::new(
-                (frame.info().window_info.size[0] - 320.0) / 2.0,
-                (frame.info().window_info.size[1] - 240.0) / 2.0,
-            ))
-            .open(&mut self.mode)
+            .default_pos(default_selection.min)
+            .open(&mut (self.mode && !self.is_compact()))
             .frame(egui::Frame {
                 stroke: egui::Stroke::new(1.5, egui::Color32::WHITE),
                 shadow: egui::epaint::Shadow::small_light(),
@@ -932,6 +2747,7 @@ impl eframe::App for MyApp {
                 ui.allocate_space(ui.available_size());
             });
 
+        let image_rect = self.image_rect(frame);
         egui::Window::new("image_viewer")
             .title_bar(false)
             .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
@@ -1130,18 +2946,91 @@ impl eframe::App for MyApp {
                                 self.selection_annotation = SelectionAnnotation::NotSelected
                             }
                         }
+                        SelectionAnnotation::Magnifier => {
+                            response
+                                .clone()
+                                .on_hover_cursor(egui::output::CursorIcon::Crosshair);
+                            let element = &mut self.annotation_element;
+                            let texture = self.texture.as_ref().unwrap().id();
+                            match element.magnifier_source {
+                                // first the detail, dragged like a rectangle or a circle
+                                None => {
+                                    if let Some(pointer_pos) = response.interact_pointer_pos() {
+                                        if response.drag_started() {
+                                            element.magnifier_origin = None;
+                                        }
+                                        let origin =
+                                            *element.magnifier_origin.get_or_insert(pointer_pos);
+                                        let source = if element.magnifier_circle {
+                                            egui::Rect::from_center_size(
+                                                origin,
+                                                egui::Vec2::splat(
+                                                    2.0 * origin.distance(pointer_pos),
+                                                ),
+                                            )
+                                        } else {
+                                            egui::Rect::from_two_pos(origin, pointer_pos)
+                                        };
+                                        if response.drag_released() {
+                                            element.magnifier_origin = None;
+                                            if source.width() >= 2.0 && source.height() >= 2.0 {
+                                                element.magnifier_source = Some(source);
+                                            }
+                                        } else if element.magnifier_circle {
+                                            painter.circle_stroke(
+                                                source.center(),
+                                                source.width() / 2.0,
+                                                element.stroke,
+                                            );
+                                        } else {
+                                            painter.rect_stroke(
+                                                source,
+                                                egui::Rounding::none(),
+                                                element.stroke,
+                                            );
+                                        }
+                                    }
+                                }
+                                // then the copy follows the pointer until it is placed
+                                Some(source) => {
+                                    if let Some(pointer_pos) =
+                                        response.interact_pointer_pos().or(response.hover_pos())
+                                    {
+                                        let inset = egui::Rect::from_center_size(
+                                            pointer_pos,
+                                            source.size() * element.magnifier_zoom,
+                                        );
+                                        let magnifier =
+                                            (source, inset, element.magnifier_circle, element.stroke);
+                                        if response.drag_released() {
+                                            element.magnifier.push(magnifier);
+                                            element.magnifier_source = None;
+                                            self.last_modify.push(SelectionAnnotation::Magnifier);
+                                        } else if let Some(image_rect) = image_rect {
+                                            paint_magnifier(&painter, texture, image_rect, magnifier);
+                                        }
+                                    }
+                                }
+                            }
+                        }
                         SelectionAnnotation::Crop => {
+                            // a crop reopened from a project starts where it was left
+                            let start = self.crop_start.unwrap_or(egui::Rect::from_min_size(
+                                egui::Pos2::new(
+                                    (frame.info().window_info.size[0] - 320.0) / 2.0,
+                                    (frame.info().window_info.size[1] - 240.0) / 2.0,
+                                ),
+                                egui::vec2(320.0, 240.0),
+                            ));
                             let pos = egui::Window::new("resize2")
+                                .id(egui::Id::new(("resize2", self.crop_id)))
                                 .title_bar(false)
-                                .default_size(egui::vec2(320.0, 240.0))
+                                .default_size(start.size())
                                 .resizable(true)
                                 .movable(true)
                                 .resize(|r| r.min_size(egui::vec2(1.0, 1.0)))
                                 .resize(|r| r.max_size(egui::vec2(dim_image.0, dim_image.1)))
-                                .default_pos(egui::Pos2::new(
-                                    (frame.info().window_info.size[0] - 320.0) / 2.0,
-                                    (frame.info().window_info.size[1] - 240.0) / 2.0,
-                                ))
+                                .default_pos(start.min)
                                 .drag_bounds(egui::Rect::from_center_size(
                                     egui::Pos2::new(
                                         (frame.info().window_info.size[0]) / 2.0,
@@ -1177,6 +3066,12 @@ impl eframe::App for MyApp {
                                 height: r.height() * adj,
                             };
                         }
+                        SelectionAnnotation::Adjust => {}
+                    }
+                }
+                if let (Some(texture), Some(image_rect)) = (&self.texture, image_rect) {
+                    for magnifier in &self.annotation_element.magnifier {
+                        paint_magnifier(&painter, texture.id(), image_rect, *magnifier);
                     }
                 }
                 let pen = self
@@ -1246,39 +3141,582 @@ impl eframe::App for MyApp {
                 painter.extend(circle);
             });
 
-        if self.mode == true {
+        if self.mode && !self.is_compact() {
             let r = w.unwrap().response.rect;
-            let mut adj = 1.0;
-            let mut mc_adj = 0.0;
-            if cfg!(target_os = "windows") {
-                adj = frame.info().native_pixels_per_point.unwrap();
-            } else if cfg!(target_os = "macos") {
-                mc_adj = frame.info().window_info.monitor_size.unwrap().y
-                    - frame.info().window_info.size.y;
-                if mc_adj > 25.0 {
-                    mc_adj = 25.0;
-                }
-            }
+            // dragging only the top or bottom edge keeps the height
+            let by_height = ((r.width() * adj) - self.screen_rect.width).abs() < 1.0
+                && ((r.height() * adj) - self.screen_rect.height).abs() >= 1.0;
+            let (width, height) =
+                self.selection_preset
+                    .constrain(r.width() * adj, r.height() * adj, by_height);
             self.screen_rect = RectangleCrop {
-                x_left: (r.left()) * adj,
-                y_left: (r.top() + mc_adj) * adj,
-                width: r.width() * adj,
-                height: r.height() * adj,
+                x_left: (r.left() * adj).round(),
+                y_left: ((r.top() + mc_adj) * adj).round(),
+                width,
+                height,
             };
+            // snap the selection window to the preset once the user releases it
+            if (((r.width() * adj) - width).abs() > 2.0 || ((r.height() * adj) - height).abs() > 2.0)
+                && !ctx.input(|i| i.pointer.any_down())
+            {
+                self.selection_id += 1;
+            }
         }
 
-        if self.get_real_monitor < 15 {
-            self.get_real_monitor += 1;
+        if !self.monitor_detected {
+            // the monitor is known once the window has been placed and maximized
+            let info = frame.info().window_info;
+            let placed = info.position.is_some() && info.monitor_size.is_some();
+            let stable = self.last_window_size == Some(info.size);
+            if placed && stable && (info.maximized || self.started.elapsed() > Duration::from_secs(1))
+            {
+                self.schermi.set_screen_no(info);
+                self.monitor_detected = true;
+                if let Some(path) = self.open_at_start.take() {
+                    self.open_file(&path, ctx, frame);
+                    // the flags become steps of the editor, so they can be undone
+                    for adjustment in std::mem::take(&mut self.start_adjustments) {
+                        self.adjust_image(|b| apply(b, &[adjustment]), ctx, frame);
+                    }
+                }
+            } else {
+                self.last_window_size = Some(info.size);
+            }
             ctx.request_repaint();
         }
-        if self.get_real_monitor == 5 {
-            self.schermi.set_screen_no(frame.info().window_info);
+
+        if let Some(elapsed) = self.recorder.as_ref().map(|r| r.elapsed().as_secs()) {
+            let mut stop = false;
+            egui::Window::new("Recording")
+                .title_bar(false)
+                .anchor(egui::Align2::RIGHT_TOP, [0.0, 10.0])
+                .frame(egui::Frame {
+                    fill: egui::Color32::GRAY,
+                    stroke: egui::Stroke::new(0.5, egui::Color32::BLACK),
+                    inner_margin: egui::style::Margin::same(15.0),
+                    rounding: egui::Rounding::same(20.0),
+                    ..Default::default()
+                })
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        let txt = format!(" ⏺ {:02}:{:02} ", elapsed / 60, elapsed % 60);
+                        ui.label(RichText::new(txt).size(20.0).color(Color32::DARK_RED));
+                        if ui.button("  Stop  ").clicked() {
+                            stop = true;
+                        }
+                    });
+                });
+            if let Some(Action::Record) = self.shortcut_set.listener(ctx, false) {
+                stop = true;
+            }
+            if self.recorder.as_ref().is_some_and(|r| r.is_full()) {
+                self.toasts.info(format!(
+                    "The recording stopped at the limit of {} frames",
+                    MAX_FRAMES
+                ));
+                stop = true;
+            }
+            if stop {
+                self.stop_recording(frame);
+            }
+            ctx.request_repaint_after(Duration::from_millis(500));
+        }
+
+        if let Some(triggers) = self.watcher.as_ref().map(|w| w.triggers()) {
+            let mut stop = false;
+            egui::Window::new("Watching")
+                .title_bar(false)
+                .anchor(egui::Align2::RIGHT_TOP, [0.0, 10.0])
+                .frame(egui::Frame {
+                    fill: egui::Color32::GRAY,
+                    stroke: egui::Stroke::new(0.5, egui::Color32::BLACK),
+                    inner_margin: egui::style::Margin::same(15.0),
+                    rounding: egui::Rounding::same(20.0),
+                    ..Default::default()
+                })
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        let txt = format!(" 👁 {} saved ", triggers);
+                        ui.label(RichText::new(txt).size(20.0).color(Color32::DARK_RED));
+                        if ui.button("  Stop  ").clicked() {
+                            stop = true;
+                        }
+                    });
+                });
+            if let Some(Action::Watch) = self.shortcut_set.listener(ctx, false) {
+                stop = true;
+            }
+            if stop {
+                self.run_action(Action::Watch, ctx, frame);
+            }
+        }
+
+        if self.show_regions && !self.image_viewer {
+            let mut use_region = None;
+            let mut capture_region = None;
+            let mut delete_region = None;
+            egui::Window::new("Saved regions")
+                .title_bar(true)
+                .frame(egui::Frame {
+                    fill: egui::Color32::GRAY,
+                    stroke: egui::Stroke::new(0.5, egui::Color32::BLACK),
+                    inner_margin: egui::style::Margin::same(15.0),
+                    rounding: egui::Rounding::same(20.0),
+                    ..Default::default()
+                })
+                .movable(true)
+                .resizable(false)
+                .open(&mut self.show_regions)
+                .show(ctx, |ui| {
+                    egui::Grid::new("regions_grid")
+                        .num_columns(5)
+                        .spacing([20.0, 4.0])
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for region in self.regions.list().iter() {
+                                ui.label(&region.name);
+                                match region.rect {
+                                    Some(rect) => ui.label(format!(
+                                        "Screen {}: {}x{} at ({}, {})",
+                                        region.screen_no,
+                                        rect.width.floor(),
+                                        rect.height.floor(),
+                                        rect.x_left.floor(),
+                                        rect.y_left.floor()
+                                    )),
+                                    None => ui.label(format!("Screen {}", region.screen_no)),
+                                };
+                                if ui
+                                    .add_enabled(
+                                        region.rect.is_none()
+                                            || region.screen_no == self.schermi.default_screen_no,
+                                        egui::Button::new("  Use  "),
+                                    )
+                                    .on_hover_text("Load the region in the selection")
+                                    .clicked()
+                                {
+                                    use_region = Some(region.id);
+                                }
+                                if ui.button("  Capture  ").clicked() {
+                                    capture_region = Some(region.id);
+                                }
+                                if ui.button("  🗑  ").clicked() {
+                                    delete_region = Some(region.id);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.regions.new_name)
+                                .hint_text("Region name"),
+                        );
+                        if ui
+                            .button("  Save current  ")
+                            .on_hover_text("Save the current screen or selection")
+                            .clicked()
+                        {
+                            let rect = if self.mode {
+                                Some(self.screen_rect)
+                            } else {
+                                None
+                            };
+                            self.regions.save_region(self.schermi.screen_no, rect);
+                        }
+                    });
+                });
+            if let Some(id) = use_region {
+                if let Some(region) = self.regions.get(id) {
+                    self.use_region(region);
+                }
+            }
+            if let Some(id) = capture_region {
+                self.run_action(Action::CaptureRegion(id), ctx, frame);
+            }
+            if let Some(id) = delete_region {
+                self.regions.delete_region(id);
+                self.shortcut_set.delete_action(Action::CaptureRegion(id));
+            }
+        }
+
+        if self.show_framing && self.annotation && self.image_viewer {
+            let mut apply_framing = false;
+            let mut browse = false;
+            if self.framing_preview.is_none() {
+                self.framing_preview = self.buffer.as_deref().and_then(|b| Preview::new(b).ok());
+            }
+            egui::Window::new("Frame")
+                .title_bar(true)
+                .frame(egui::Frame {
+                    fill: egui::Color32::GRAY,
+                    stroke: egui::Stroke::new(0.5, egui::Color32::BLACK),
+                    inner_margin: egui::style::Margin::same(15.0),
+                    rounding: egui::Rounding::same(20.0),
+                    ..Default::default()
+                })
+                .movable(true)
+                .resizable(false)
+                .open(&mut self.show_framing)
+                .show(ctx, |ui| {
+                    let preset = presets()
+                        .into_iter()
+                        .find(|(_, background)| *background == self.framing.background)
+                        .map_or("Custom", |(name, _)| name);
+                    egui::ComboBox::from_label("Background")
+                        .selected_text(preset)
+                        .show_ui(ui, |ui| {
+                            for (name, background) in presets() {
+                                if ui
+                                    .selectable_label(self.framing.background == background, name)
+                                    .clicked()
+                                {
+                                    self.framing.background = background;
+                                }
+                            }
+                        });
+                    // the colors are kept when the kind of background changes
+                    let background = &mut self.framing.background;
+                    let first = match background {
+                        Background::Solid(color) | Background::Gradient(color, _) => *color,
+                        Background::Image(_) => [255, 255, 255, 255],
+                    };
+                    ui.horizontal(|ui| {
+                        if ui
+                            .radio(matches!(background, Background::Solid(_)), "Solid")
+                            .clicked()
+                        {
+                            *background = Background::Solid(first);
+                        }
+                        if ui
+                            .radio(matches!(background, Background::Gradient(..)), "Gradient")
+                            .clicked()
+                            && !matches!(background, Background::Gradient(..))
+                        {
+                            *background = Background::Gradient(first, [255, 255, 255, 255]);
+                        }
+                        if ui
+                            .radio(matches!(background, Background::Image(_)), "Image")
+                            .clicked()
+                            && !matches!(background, Background::Image(_))
+                        {
+                            *background = Background::Image(String::new());
+                        }
+                    });
+                    ui.horizontal(|ui| match background {
+                        Background::Solid(color) => {
+                            ui.color_edit_button_srgba_unmultiplied(color);
+                        }
+                        Background::Gradient(from, to) => {
+                            ui.color_edit_button_srgba_unmultiplied(from);
+                            ui.label("→");
+                            ui.color_edit_button_srgba_unmultiplied(to);
+                        }
+                        Background::Image(path) => {
+                            ui.add(
+                                egui::TextEdit::singleline(path)
+                                    .hint_text("background image")
+                                    .desired_width(250.0),
+                            );
+                            browse = ui.button("  📂  ").clicked();
+                        }
+                    });
+                    ui.add_space(5.0);
+                    egui::Grid::new("framing_grid").show(ui, |ui| {
+                        ui.label("Padding:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.framing.padding)
+                                .clamp_range(0..=1000)
+                                .suffix(" px"),
+                        );
+                        ui.end_row();
+                        ui.label("Corner radius:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.framing.radius)
+                                .clamp_range(0..=500)
+                                .suffix(" px"),
+                        );
+                        ui.end_row();
+                        ui.label("Shadow:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.framing.shadow)
+                                .clamp_range(0..=500)
+                                .suffix(" px"),
+                        );
+                        ui.end_row();
+                    });
+                    ui.checkbox(&mut self.framing.chrome, "Window title bar");
+                    ui.add_space(5.0);
+                    // the preview is small and without the annotations, the result is not
+                    match self
+                        .framing_preview
+                        .as_mut()
+                        .map(|p| p.texture(ctx, &self.framing))
+                    {
+                        Some(Ok(texture)) => {
+                            ui.image(&texture, texture.size_vec2());
+                        }
+                        Some(Err(error)) => {
+                            ui.label(error.to_string());
+                        }
+                        None => {}
+                    }
+                    ui.add_space(5.0);
+                    if ui
+                        .button("  Apply  ")
+                        .on_hover_text("Frame the image at its own resolution")
+                        .clicked()
+                    {
+                        apply_framing = true;
+                    }
+                });
+            if browse {
+                match FileDialog::new()
+                    .add_filter("Images", &["png", "jpg", "jpeg", "gif", "bmp", "webp"])
+                    .show_open_single_file()
+                {
+                    Ok(Some(path)) => {
+                        self.framing.background = Background::Image(path.display().to_string())
+                    }
+                    Ok(None) => {}
+                    Err(error) => self.report(AppError::Dialog(error.to_string()), None),
+                }
+            }
+            if apply_framing {
+                let framing = self.framing.clone();
+                self.adjust_image(|b| beautify::beautify::apply(b, &framing), ctx, frame);
+                self.show_framing = false;
+            }
+        }
+
+        if self.show_history && !self.image_viewer {
+            let mut open_entry = None;
+            let mut copy_entry = None;
+            let mut save_entry = None;
+            let mut delete_entry = None;
+            let mut apply_limits = false;
+            let mut prune_confirmed = false;
+            egui::Window::new("History")
+                .title_bar(true)
+                .frame(egui::Frame {
+                    fill: egui::Color32::GRAY,
+                    stroke: egui::Stroke::new(0.5, egui::Color32::BLACK),
+                    inner_margin: egui::style::Margin::same(15.0),
+                    rounding: egui::Rounding::same(20.0),
+                    ..Default::default()
+                })
+                .movable(true)
+                .resizable(false)
+                .open(&mut self.show_history)
+                .show(ctx, |ui| {
+                    if self.history.entries().is_empty() {
+                        ui.label("No captures yet");
+                    }
+                    egui::ScrollArea::vertical()
+                        .max_height(500.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("history_grid")
+                                .num_columns(6)
+                                .spacing([20.0, 4.0])
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for i in 0..self.history.entries().len() {
+                                        match self.history.thumbnail(ctx, i) {
+                                            Some(texture) => {
+                                                // thumbnails are at most 160 pixels wide or tall
+                                                let size = texture.size_vec2() * 0.5;
+                                                ui.image(&texture, size)
+                                            }
+                                            None => ui.label("?"),
+                                        };
+                                        let entry = &self.history.entries()[i];
+                                        ui.label(format!(
+                                            "{}\nScreen {}, {}\n{}x{}",
+                                            entry.time(),
+                                            entry.screen + 1,
+                                            entry.mode,
+                                            entry.width,
+                                            entry.height
+                                        ));
+                                        if ui
+                                            .button("  Open  ")
+                                            .on_hover_text("Open in the annotation editor")
+                                            .clicked()
+                                        {
+                                            open_entry = Some(i);
+                                        }
+                                        if ui.button("  📋  ").on_hover_text("Copy").clicked() {
+                                            copy_entry = Some(i);
+                                        }
+                                        if ui.button("  💾  ").on_hover_text("Save").clicked() {
+                                            save_entry = Some(i);
+                                        }
+                                        if ui.button("  🗑  ").on_hover_text("Delete").clicked() {
+                                            delete_entry = Some(i);
+                                        }
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        ui.label("Keep the last");
+                        ui.add(
+                            egui::DragValue::new(&mut self.history_limits.0)
+                                .clamp_range(0..=1000)
+                                .custom_formatter(|n, _| {
+                                    if n == 0.0 {
+                                        "∞".to_string()
+                                    } else {
+                                        format!("{}", n)
+                                    }
+                                }),
+                        );
+                        ui.label("captures, for");
+                        ui.add(
+                            egui::DragValue::new(&mut self.history_limits.1)
+                                .clamp_range(0..=365)
+                                .custom_formatter(|n, _| {
+                                    if n == 0.0 {
+                                        "∞".to_string()
+                                    } else {
+                                        format!("{}", n)
+                                    }
+                                }),
+                        );
+                        ui.label("days");
+                        let changed =
+                            self.history_limits != (self.history.max_count, self.history.max_days);
+                        if ui.add_enabled(changed, egui::Button::new("Apply")).clicked() {
+                            apply_limits = true;
+                        }
+                    });
+                    if let Some(count) = self.confirm_prune {
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                RichText::new(format!("{} captures will be deleted", count))
+                                    .color(Color32::DARK_RED),
+                            );
+                            if ui.button("Delete").clicked() {
+                                prune_confirmed = true;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                self.confirm_prune = None;
+                            }
+                        });
+                    }
+                });
+            let (max_count, max_days) = self.history_limits;
+            if apply_limits {
+                // nothing is deleted before the user agrees
+                match self.history.beyond_limits(max_count, max_days) {
+                    0 => prune_confirmed = true,
+                    count => self.confirm_prune = Some(count),
+                }
+            }
+            if prune_confirmed {
+                self.history.max_count = max_count;
+                self.history.max_days = max_days;
+                self.history.prune();
+                self.confirm_prune = None;
+            }
+            if let Some(i) = open_entry {
+                match self.history.read(i) {
+                    Ok(buffer) => {
+                        let entry = &self.history.entries()[i];
+                        self.capture_info.screen = entry.screen;
+                        self.capture_info.width = entry.width;
+                        self.capture_info.height = entry.height;
+                        self.show_image(buffer, ctx);
+                        self.annotation = self.image_viewer;
+                    }
+                    Err(error) => self.report(error, None),
+                }
+            }
+            if let Some(i) = copy_entry {
+                if let Err(error) = self
+                    .history
+                    .read(i)
+                    .and_then(|b| copy(&b, CopyFormat::Image, None))
+                {
+                    self.report(error, None);
+                }
+            }
+            if let Some(i) = save_entry {
+                let entry = &self.history.entries()[i];
+                let entry_metadata = Metadata {
+                    timestamp: entry.timestamp,
+                    screen: entry.screen,
+                    mode: entry.mode.clone(),
+                    width: entry.width,
+                    height: entry.height,
+                    window_title: String::new(),
+                };
+                match self.history.read(i) {
+                    Ok(buffer) => match self.choose_save_path(&[("PNG Image", &["png"])]) {
+                        Ok(Some(path)) => match self.write_png(&path, &buffer, &entry_metadata) {
+                            Ok(message) => {
+                                self.name_used();
+                                self.toasts.info(message)
+                            }
+                            Err(error) => self.report(error, None),
+                        },
+                        Ok(None) => {}
+                        Err(error) => self.report(error, None),
+                    },
+                    Err(error) => self.report(error, None),
+                }
+            }
+            if let Some(i) = delete_entry {
+                self.history.delete(i);
+            }
+        }
+
+        if self.interval.is_running() {
+            let mut stop = false;
+            egui::Window::new("Interval")
+                .title_bar(false)
+                .anchor(egui::Align2::RIGHT_TOP, [0.0, 10.0])
+                .frame(egui::Frame {
+                    fill: egui::Color32::GRAY,
+                    stroke: egui::Stroke::new(0.5, egui::Color32::BLACK),
+                    inner_margin: egui::style::Margin::same(15.0),
+                    rounding: egui::Rounding::same(20.0),
+                    ..Default::default()
+                })
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        let shots = if self.interval.shots == 0 {
+                            "∞".to_string()
+                        } else {
+                            self.interval.shots.to_string()
+                        };
+                        let txt = format!(
+                            "  {}/{}  next in {}  ",
+                            self.interval.taken(),
+                            shots,
+                            format_duration(self.interval.next_in())
+                        );
+                        ui.label(RichText::new(txt).size(20.0).color(Color32::DARK_RED));
+                        if ui.button("  Stop  ").clicked() {
+                            stop = true;
+                        }
+                    });
+                });
+            if stop {
+                self.interval.stop();
+            }
+            ctx.request_repaint_after(Duration::from_millis(200));
         }
 
         if self.timer.is_timer_running() {
+            let (align, offset) = self.countdown_position.anchor();
             egui::Window::new("Countdown")
                 .title_bar(false)
-                .anchor(egui::Align2::RIGHT_TOP, [0.0, 10.0])
+                .anchor(align, offset)
                 .frame(egui::Frame {
                     fill: egui::Color32::GRAY,
                     stroke: egui::Stroke::new(0.5, egui::Color32::BLACK),
@@ -1288,13 +3726,210 @@ impl eframe::App for MyApp {
                 })
                 .resizable(false)
                 .show(ctx, |ui| {
-                    let txt = format!("  {}  ", self.timer.get_seconds() - 1);
+                    let txt = format!("  {}  ", format_duration(self.timer.remaining()));
                     ui.label(RichText::new(txt).size(40.0).color(Color32::DARK_RED));
                 });
         }
     }
 }
 
+// a wav file chooser with a button to listen to it
+fn wav_picker(ui: &mut egui::Ui, wav: &mut String, sound: Sound) {
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.add(egui::TextEdit::singleline(wav).hint_text("file.wav"));
+        if ui.button("Change").clicked() {
+            if let Ok(Some(path)) = FileDialog::new()
+                .add_filter("WAV audio", &["wav"])
+                .show_open_single_file()
+            {
+                *wav = path.to_string_lossy().to_string();
+            }
+        }
+        if ui.button(" ▶ ").clicked() {
+            play(sound, wav);
+        }
+    });
+}
+
+// the copy formats, in a menu; the copy is done by the caller
+fn copy_menu(ui: &mut egui::Ui, title: &str) -> Option<CopyFormat> {
+    let mut chosen = None;
+    ui.menu_button(title, |ui| {
+        for format in CopyFormat::all() {
+            if ui.button(format.to_string()).clicked() {
+                chosen = Some(format);
+                ui.close_menu();
+            }
+        }
+    })
+    .response
+    .on_hover_text("Copy as");
+    chosen
+}
+
+fn adjust_menu(ui: &mut egui::Ui, settings: &mut AdjustSettings) -> Option<Adjustment> {
+    let mut chosen = None;
+    ui.menu_button("  🔧  ", |ui| {
+        ui.horizontal(|ui| {
+            if ui.button("Rotate ⟲").clicked() {
+                chosen = Some(Adjustment::Rotate(270));
+            }
+            if ui.button("Rotate ⟳").clicked() {
+                chosen = Some(Adjustment::Rotate(90));
+            }
+            if ui.button("Flip ↔").clicked() {
+                chosen = Some(Adjustment::FlipHorizontal);
+            }
+            if ui.button("Flip ↕").clicked() {
+                chosen = Some(Adjustment::FlipVertical);
+            }
+        });
+        egui::Grid::new("adjustments").show(ui, |ui| {
+            if ui.button("Resize").clicked() {
+                chosen = Some(Adjustment::Resize(settings.max_width));
+            }
+            ui.horizontal(|ui| {
+                ui.label("max width");
+                ui.add(
+                    egui::DragValue::new(&mut settings.max_width)
+                        .clamp_range(1..=20000)
+                        .suffix(" px"),
+                );
+            });
+            ui.end_row();
+            if ui.button("Border").clicked() {
+                chosen = Some(Adjustment::Border(settings.border, settings.border_color));
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut settings.border)
+                        .clamp_range(1..=500)
+                        .suffix(" px"),
+                );
+                let [r, g, b, a] = settings.border_color;
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(16.0, 16.0), egui::Sense::hover());
+                ui.painter().rect_filled(
+                    rect,
+                    egui::Rounding::same(2.0),
+                    Color32::from_rgba_unmultiplied(r, g, b, a),
+                );
+            });
+            ui.end_row();
+            if ui.button("Rounded corners").clicked() {
+                chosen = Some(Adjustment::RoundCorners(settings.radius));
+            }
+            ui.add(
+                egui::DragValue::new(&mut settings.radius)
+                    .clamp_range(1..=500)
+                    .suffix(" px"),
+            );
+            ui.end_row();
+            if ui.button("Shadow").clicked() {
+                chosen = Some(Adjustment::Shadow(settings.shadow));
+            }
+            ui.add(
+                egui::DragValue::new(&mut settings.shadow)
+                    .clamp_range(1..=500)
+                    .suffix(" px"),
+            );
+            ui.end_row();
+        });
+        ui.collapsing("Border colour", |ui| color_picker(ui, &mut settings.border_color));
+        if chosen.is_some() {
+            ui.close_menu();
+        }
+    })
+    .response
+    .on_hover_text("Resize, rotate, flip, border, rounded corners and shadow");
+    chosen
+}
+
+// the color popups close the menus they are in, so the picker is drawn inline
+fn color_picker(ui: &mut egui::Ui, rgba: &mut [u8; 4]) {
+    let [r, g, b, a] = *rgba;
+    let mut color = Color32::from_rgba_unmultiplied(r, g, b, a);
+    if egui::color_picker::color_picker_color32(
+        ui,
+        &mut color,
+        egui::color_picker::Alpha::OnlyBlend,
+    ) {
+        *rgba = color.to_srgba_unmultiplied();
+    }
+}
+
+// the copy samples the texture of the whole capture, so it is as sharp as the capture
+fn paint_magnifier(
+    painter: &egui::Painter,
+    texture: egui::TextureId,
+    image_rect: egui::Rect,
+    (source, inset, circle, stroke): (egui::Rect, egui::Rect, bool, egui::Stroke),
+) {
+    let uv = |p: egui::Pos2| {
+        egui::pos2(
+            (p.x - image_rect.left()) / image_rect.width(),
+            (p.y - image_rect.top()) / image_rect.height(),
+        )
+    };
+    let outline = |rect: egui::Rect| {
+        if circle {
+            egui::Shape::circle_stroke(rect.center(), rect.width() / 2.0, stroke)
+        } else {
+            egui::Shape::rect_stroke(rect, egui::Rounding::none(), stroke)
+        }
+    };
+    painter.add(outline(source));
+    let project_rect = |r: egui::Rect| project::project::Rect {
+        x: r.left(),
+        y: r.top(),
+        width: r.width(),
+        height: r.height(),
+    };
+    if let Some((a, b)) = magnifier_link(project_rect(source), project_rect(inset), circle) {
+        painter.line_segment([egui::pos2(a.x, a.y), egui::pos2(b.x, b.y)], stroke);
+    }
+    let mut mesh = egui::Mesh::with_texture(texture);
+    if circle {
+        let segments = 64;
+        let vertex = |pos, uv| egui::epaint::Vertex {
+            pos,
+            uv,
+            color: Color32::WHITE,
+        };
+        mesh.vertices.push(vertex(inset.center(), uv(source.center())));
+        for i in 0..=segments {
+            let angle = std::f32::consts::TAU * i as f32 / segments as f32;
+            let direction = egui::vec2(angle.cos(), angle.sin());
+            mesh.vertices.push(vertex(
+                inset.center() + direction * inset.width() / 2.0,
+                uv(source.center() + direction * source.width() / 2.0),
+            ));
+            if i > 0 {
+                mesh.add_triangle(0, i, i + 1);
+            }
+        }
+    } else {
+        mesh.add_rect_with_uv(
+            inset,
+            egui::Rect::from_min_max(uv(source.min), uv(source.max)),
+            Color32::WHITE,
+        );
+    }
+    painter.add(mesh);
+    painter.add(outline(inset));
+}
+
+// a rectangle of the editor in pixels of the capture
+fn pixel_rect(rect: egui::Rect, point: &impl Fn(egui::Pos2) -> Point) -> project::project::Rect {
+    let (min, max) = (point(rect.min), point(rect.max));
+    project::project::Rect {
+        x: min.x,
+        y: min.y,
+        width: max.x - min.x,
+        height: max.y - min.y,
+    }
+}
+
 fn load_image_from_memory(image_data: &[u8]) -> Result<egui::ColorImage, image::ImageError> {
     let image = image::load_from_memory(image_data)?;
     let size = [image.width() as _, image.height() as _];
@@ -1326,12 +3961,36 @@ fn resize_image_to_fit_container(
     }
 }
 
-fn compute_default_name() -> String {
-    let today = Local::now()
-        .to_string()
-        .replace("-", "")
-        .replace(":", "_")
-        .replace(" ", "")
-        .to_string();
-    format!("screenshot_{}", today)[..27].to_string()
+fn screen_adjustment(frame: &eframe::Frame) -> (f32, f32) {
+    let mut adj = 1.0;
+    let mut mc_adj = 0.0;
+    if cfg!(target_os = "windows") {
+        adj = frame.info().native_pixels_per_point.unwrap();
+    } else if cfg!(target_os = "macos") {
+        mc_adj = frame.info().window_info.monitor_size.unwrap().y - frame.info().window_info.size.y;
+        if mc_adj > 25.0 {
+            mc_adj = 25.0;
+        }
+    }
+    (adj, mc_adj)
+}
+
+// the rest of the app expects png buffers
+fn to_png(buffer: &[u8]) -> Result<Vec<u8>, AppError> {
+    if let Ok(image::ImageFormat::Png) = image::guess_format(buffer) {
+        return Ok(buffer.to_vec());
+    }
+    let image = image::load_from_memory(buffer).map_err(|e| AppError::Image(e.to_string()))?;
+    let mut png = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .map_err(|e| AppError::Image(e.to_string()))?;
+    Ok(png)
+}
+
+// reads only the header of the image
+fn image_size(buffer: &[u8]) -> Result<(u32, u32), image::ImageError> {
+    image::io::Reader::new(std::io::Cursor::new(buffer))
+        .with_guessed_format()?
+        .into_dimensions()
 }
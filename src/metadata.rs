@@ -0,0 +1,544 @@
+pub mod metadata {
+    use crate::error::error::AppError;
+    use crate::project::project::Metadata;
+    use chrono::{Local, TimeZone};
+    use std::fs;
+    use std::process::Command;
+
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    const EXIF_HEADER: &[u8] = b"Exif\0\0";
+    const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+    // png keywords where possible, so other programs show them
+    const TIME: &str = "Creation Time";
+    const SOFTWARE: &str = "Software";
+    const HOSTNAME: &str = "Hostname";
+    const DESCRIPTION: &str = "Description";
+    const TAGS: &str = "Keywords";
+
+    // where a capture comes from, as keyword and value; empty values are left out
+    pub fn fields(
+        metadata: &Metadata,
+        description: &str,
+        tags: &str,
+        hostname: bool,
+        extra: &[(String, String)],
+    ) -> Vec<(String, String)> {
+        let mut fields = vec![(
+            SOFTWARE.to_string(),
+            format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        )];
+        let time = Local.timestamp_millis_opt(metadata.timestamp).single();
+        if let Some(time) = time.filter(|_| metadata.timestamp > 0) {
+            fields.push((TIME.to_string(), time.to_rfc3339()));
+        }
+        // the computer name can identify the user, it is left out unless asked for
+        if hostname {
+            fields.push((HOSTNAME.to_string(), self::hostname()));
+        }
+        if !metadata.mode.is_empty() {
+            fields.push(("Screen".to_string(), metadata.screen.to_string()));
+            fields.push(("Mode".to_string(), metadata.mode.clone()));
+        }
+        if metadata.width > 0 && metadata.height > 0 {
+            fields.push((
+                "Resolution".to_string(),
+                format!("{}x{}", metadata.width, metadata.height),
+            ));
+        }
+        fields.push((DESCRIPTION.to_string(), description.trim().to_string()));
+        let tags: Vec<&str> = tags
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .collect();
+        fields.push((TAGS.to_string(), tags.join(", ")));
+        // the values given on the command line replace the ones above
+        for (key, value) in extra {
+            fields.retain(|(k, _)| k != key);
+            fields.push((key.clone(), value.clone()));
+        }
+        fields.retain(|(_, value)| !value.is_empty());
+        fields
+    }
+
+    pub fn hostname() -> String {
+        if let Ok(name) = std::env::var("COMPUTERNAME") {
+            return name;
+        }
+        fs::read_to_string("/proc/sys/kernel/hostname")
+            .or_else(|_| fs::read_to_string("/etc/hostname"))
+            .ok()
+            .or_else(|| {
+                Command::new("hostname")
+                    .output()
+                    .ok()
+                    .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+            })
+            .map(|name| name.trim().to_string())
+            .unwrap_or_default()
+    }
+
+    // a key=value of the command line; the key must be a valid png keyword
+    pub fn parse_field(text: &str) -> Result<(String, String), String> {
+        let (key, value) = text
+            .split_once('=')
+            .ok_or(format!("--meta: '{}' is not like key=value", text))?;
+        let key = key.trim();
+        if key.is_empty()
+            || key.len() > 79
+            || !key.chars().all(|c| c.is_ascii_graphic() || c == ' ')
+        {
+            return Err(format!(
+                "--meta: '{}' must be 1 to 79 ascii letters, digits, symbols or spaces",
+                key
+            ));
+        }
+        Ok((key.to_string(), value.to_string()))
+    }
+
+    // png gets text chunks, jpeg exif and xmp; other formats are left as they are
+    pub fn embed(buffer: &[u8], fields: &[(String, String)]) -> Result<Vec<u8>, AppError> {
+        if fields.is_empty() {
+            return Ok(buffer.to_vec());
+        }
+        if buffer.starts_with(PNG_SIGNATURE) {
+            embed_png(buffer, fields)
+        } else if buffer.starts_with(&[0xff, 0xd8]) {
+            embed_jpeg(buffer, fields)
+        } else {
+            Ok(buffer.to_vec())
+        }
+    }
+
+    // the chunks go right after the header, replacing older ones with the same keyword
+    fn embed_png(buffer: &[u8], fields: &[(String, String)]) -> Result<Vec<u8>, AppError> {
+        let invalid = || AppError::Image("the png is truncated".to_string());
+        let mut output = PNG_SIGNATURE.to_vec();
+        let mut position = PNG_SIGNATURE.len();
+        while position < buffer.len() {
+            let length = buffer
+                .get(position..position + 4)
+                .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+                .ok_or_else(invalid)?;
+            let end = position + 12 + length;
+            let chunk = buffer.get(position..end).ok_or_else(invalid)?;
+            let kind = &chunk[4..8];
+            let data = &chunk[8..8 + length];
+            let replaced = matches!(kind, b"tEXt" | b"zTXt" | b"iTXt")
+                && fields
+                    .iter()
+                    .any(|(key, _)| data.split(|&b| b == 0).next() == Some(key.as_bytes()));
+            if !replaced {
+                output.extend_from_slice(chunk);
+            }
+            if kind == b"IHDR" {
+                for (key, value) in fields {
+                    text_chunk(&mut output, key, value);
+                }
+            }
+            position = end;
+        }
+        Ok(output)
+    }
+
+    // tEXt is latin-1, so anything else goes in an uncompressed iTXt as utf-8
+    fn text_chunk(output: &mut Vec<u8>, key: &str, value: &str) {
+        let mut data = key.as_bytes().to_vec();
+        data.push(0);
+        let kind = if value.is_ascii() {
+            b"tEXt"
+        } else {
+            // compression flag, method, empty language and translated keyword
+            data.extend_from_slice(&[0, 0, 0, 0]);
+            b"iTXt"
+        };
+        data.extend_from_slice(value.as_bytes());
+        output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let mut crc = flate2::Crc::new();
+        crc.update(kind);
+        crc.update(&data);
+        output.extend_from_slice(kind);
+        output.extend_from_slice(&data);
+        output.extend_from_slice(&crc.sum().to_be_bytes());
+    }
+
+    // exif and xmp are app1 segments after soi and the jfif app0; older ones are dropped
+    fn embed_jpeg(buffer: &[u8], fields: &[(String, String)]) -> Result<Vec<u8>, AppError> {
+        let invalid = || AppError::Image("the jpeg is truncated".to_string());
+        let mut position = 2;
+        let mut app0 = Vec::new();
+        let mut others = Vec::new();
+        // the segments before the image data, which starts at sos
+        while position + 4 <= buffer.len() && buffer[position] == 0xff {
+            let marker = buffer[position + 1];
+            if marker == 0xda {
+                break;
+            }
+            let length = u16::from_be_bytes([buffer[position + 2], buffer[position + 3]]) as usize;
+            let segment = buffer
+                .get(position..position + 2 + length)
+                .filter(|_| length >= 2)
+                .ok_or_else(invalid)?;
+            let data = &segment[4..];
+            match marker {
+                0xe0 => app0.extend_from_slice(segment),
+                0xe1 if data.starts_with(EXIF_HEADER) || data.starts_with(XMP_HEADER) => {}
+                _ => others.extend_from_slice(segment),
+            }
+            position += 2 + length;
+        }
+        let mut output = vec![0xff, 0xd8];
+        output.extend_from_slice(&app0);
+        for payload in [exif(fields), xmp(fields)] {
+            // the length of a segment has 16 bits, the rest would not fit
+            if payload.len() + 2 <= u16::MAX as usize {
+                output.extend_from_slice(&[0xff, 0xe1]);
+                output.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+                output.extend_from_slice(&payload);
+            }
+        }
+        output.extend_from_slice(&others);
+        output.extend_from_slice(&buffer[position..]);
+        Ok(output)
+    }
+
+    // a little endian tiff with the fields that exif has in ifd0
+    fn exif(fields: &[(String, String)]) -> Vec<u8> {
+        let value = |key: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.clone())
+        };
+        let time = value(TIME)
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(&t).ok())
+            .map(|t| t.format("%Y:%m:%d %H:%M:%S").to_string());
+        // sorted by tag, as tiff requires
+        let entries: Vec<(u16, String)> = [
+            (0x010e, value(DESCRIPTION)), // ImageDescription
+            (0x0131, value(SOFTWARE)),
+            (0x0132, time),            // DateTime
+            (0x013c, value(HOSTNAME)), // HostComputer
+        ]
+        .into_iter()
+        .filter_map(|(tag, value)| value.map(|v| (tag, v)))
+        .collect();
+        let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+        let mut strings = Vec::new();
+        let strings_offset = 8 + 2 + 12 * entries.len() + 4;
+        tiff.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        for (tag, value) in &entries {
+            // exif strings are ascii and end with a nul
+            let mut text: Vec<u8> = value
+                .chars()
+                .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+                .collect();
+            text.push(0);
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&2u16.to_le_bytes());
+            tiff.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            if text.len() <= 4 {
+                text.resize(4, 0);
+                tiff.extend_from_slice(&text);
+            } else {
+                tiff.extend_from_slice(&((strings_offset + strings.len()) as u32).to_le_bytes());
+                strings.extend_from_slice(&text);
+                if strings.len() % 2 == 1 {
+                    strings.push(0);
+                }
+            }
+        }
+        tiff.extend_from_slice(&0u32.to_le_bytes()); // no other ifd
+        tiff.extend_from_slice(&strings);
+        let mut payload = EXIF_HEADER.to_vec();
+        payload.extend_from_slice(&tiff);
+        payload
+    }
+
+    // the known fields with the names other programs look for, the rest as key and value
+    fn xmp(fields: &[(String, String)]) -> Vec<u8> {
+        let mut properties = String::new();
+        let mut others = String::new();
+        for (key, value) in fields {
+            let value = escape(value);
+            let property = match key.as_str() {
+                TIME => format!("<xmp:CreateDate>{}</xmp:CreateDate>", value),
+                SOFTWARE => format!("<xmp:CreatorTool>{}</xmp:CreatorTool>", value),
+                DESCRIPTION => format!(
+                    "<dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:description>",
+                    value
+                ),
+                TAGS => format!(
+                    "<dc:subject><rdf:Bag>{}</rdf:Bag></dc:subject>",
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(|tag| format!("<rdf:li>{}</rdf:li>", tag))
+                        .collect::<String>()
+                ),
+                _ => {
+                    others.push_str(&format!(
+                        "<rdf:li rdf:parseType=\"Resource\"><screenshot:key>{}</screenshot:key><screenshot:value>{}</screenshot:value></rdf:li>",
+                        escape(key),
+                        value
+                    ));
+                    continue;
+                }
+            };
+            properties.push_str("\n   ");
+            properties.push_str(&property);
+        }
+        if !others.is_empty() {
+            properties.push_str(&format!(
+                "\n   <screenshot:fields><rdf:Bag>{}</rdf:Bag></screenshot:fields>",
+                others
+            ));
+        }
+        let packet = format!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n \
+<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n  \
+<rdf:Description rdf:about=\"\" xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" \
+xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+xmlns:screenshot=\"urn:screenshot:metadata:1\">{}\n  \
+</rdf:Description>\n \
+</rdf:RDF>\n\
+</x:xmpmeta>\n\
+<?xpacket end=\"w\"?>",
+            properties
+        );
+        let mut payload = XMP_HEADER.to_vec();
+        payload.extend_from_slice(packet.as_bytes());
+        payload
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::adjust::adjust::encode_jpeg;
+        use image::RgbaImage;
+        use std::io::Cursor;
+
+        fn image() -> RgbaImage {
+            RgbaImage::from_fn(16, 8, |x, y| {
+                image::Rgba([(x * 16) as u8, (y * 32) as u8, 90, 255])
+            })
+        }
+
+        fn png() -> Vec<u8> {
+            let mut buffer = Vec::new();
+            image::DynamicImage::ImageRgba8(image())
+                .write_to(&mut Cursor::new(&mut buffer), image::ImageOutputFormat::Png)
+                .unwrap();
+            buffer
+        }
+
+        fn field(key: &str, value: &str) -> (String, String) {
+            (key.to_string(), value.to_string())
+        }
+
+        // the chunks of a png, kind and data
+        fn chunks(buffer: &[u8]) -> Vec<(String, Vec<u8>)> {
+            let mut chunks = Vec::new();
+            let mut position = PNG_SIGNATURE.len();
+            while position < buffer.len() {
+                let b = &buffer[position..];
+                let length = u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize;
+                let kind = String::from_utf8(b[4..8].to_vec()).unwrap();
+                chunks.push((kind, b[8..8 + length].to_vec()));
+                position += 12 + length;
+            }
+            chunks
+        }
+
+        // the marker and the data of the segments before the image data
+        fn segments(buffer: &[u8]) -> Vec<(u8, Vec<u8>)> {
+            assert_eq!(&buffer[..2], &[0xff, 0xd8]);
+            let mut segments = Vec::new();
+            let mut position = 2;
+            while buffer[position + 1] != 0xda {
+                assert_eq!(buffer[position], 0xff);
+                let length =
+                    u16::from_be_bytes([buffer[position + 2], buffer[position + 3]]) as usize;
+                segments.push((
+                    buffer[position + 1],
+                    buffer[position + 4..position + 2 + length].to_vec(),
+                ));
+                position += 2 + length;
+            }
+            segments
+        }
+
+        #[test]
+        fn png_text_chunks_follow_the_header() {
+            let fields = vec![
+                field(SOFTWARE, "screenshot 1.0"),
+                field(DESCRIPTION, "menu è tagliato"),
+            ];
+            let embedded = embed(&png(), &fields).unwrap();
+            let kinds: Vec<String> = chunks(&embedded).into_iter().map(|c| c.0).collect();
+            assert_eq!(&kinds[..3], &["IHDR", "tEXt", "iTXt"]);
+            // the png crate checks every crc
+            let reader = png::Decoder::new(Cursor::new(&embedded))
+                .read_info()
+                .unwrap();
+            let info = reader.info();
+            assert_eq!(info.uncompressed_latin1_text[0].keyword, SOFTWARE);
+            assert_eq!(info.uncompressed_latin1_text[0].text, "screenshot 1.0");
+            assert_eq!(info.utf8_text[0].keyword, DESCRIPTION);
+            assert_eq!(info.utf8_text[0].get_text().unwrap(), "menu è tagliato");
+            let decoded = image::load_from_memory(&embedded).unwrap().to_rgba8();
+            assert_eq!(decoded, image());
+        }
+
+        #[test]
+        fn png_embedding_again_replaces_the_same_keywords() {
+            let first = embed(&png(), &[field(SOFTWARE, "a"), field("Ticket", "1")]).unwrap();
+            let second = embed(&first, &[field(SOFTWARE, "b")]).unwrap();
+            let texts: Vec<Vec<u8>> = chunks(&second)
+                .into_iter()
+                .filter(|c| c.0 == "tEXt")
+                .map(|c| c.1)
+                .collect();
+            assert_eq!(texts, vec![b"Software\x00b".to_vec(), b"Ticket\x001".to_vec()]);
+        }
+
+        #[test]
+        fn png_truncated_is_an_error() {
+            let buffer = png();
+            assert!(embed(&buffer[..buffer.len() - 5], &[field(SOFTWARE, "a")]).is_err());
+        }
+
+        #[test]
+        fn nothing_to_embed_leaves_the_file_alone() {
+            assert_eq!(embed(&png(), &[]).unwrap(), png());
+            let gif = b"GIF89a".to_vec();
+            assert_eq!(embed(&gif, &[field(SOFTWARE, "a")]).unwrap(), gif);
+        }
+
+        #[test]
+        fn exif_offsets_point_to_the_strings() {
+            let fields = vec![
+                field(SOFTWARE, "screenshot 1.0"),
+                field(TIME, "2023-10-20T12:30:00+02:00"),
+                field(DESCRIPTION, "età"),
+                field(HOSTNAME, "pc"),
+            ];
+            let payload = exif(&fields);
+            assert!(payload.starts_with(EXIF_HEADER));
+            let tiff = &payload[EXIF_HEADER.len()..];
+            assert_eq!(&tiff[..8], b"II*\0\x08\0\0\0");
+            let u16_at = |i: usize| u16::from_le_bytes([tiff[i], tiff[i + 1]]);
+            let u32_at =
+                |i: usize| u32::from_le_bytes([tiff[i], tiff[i + 1], tiff[i + 2], tiff[i + 3]]);
+            let count = u16_at(8) as usize;
+            let mut entries = Vec::new();
+            for i in 0..count {
+                let entry = 10 + i * 12;
+                assert_eq!(u16_at(entry + 2), 2); // ascii
+                let length = u32_at(entry + 4) as usize;
+                let start = if length <= 4 {
+                    entry + 8
+                } else {
+                    u32_at(entry + 8) as usize
+                };
+                let text = &tiff[start..start + length];
+                assert_eq!(text.last(), Some(&0));
+                entries.push((
+                    u16_at(entry),
+                    String::from_utf8(text[..length - 1].to_vec()).unwrap(),
+                ));
+            }
+            assert_eq!(u32_at(10 + count * 12), 0);
+            assert_eq!(
+                entries,
+                vec![
+                    (0x010e, "et?".to_string()),
+                    (0x0131, "screenshot 1.0".to_string()),
+                    (0x0132, "2023:10:20 12:30:00".to_string()),
+                    (0x013c, "pc".to_string()),
+                ]
+            );
+        }
+
+        #[test]
+        fn jpeg_segments_are_rewritten() {
+            let jpeg = encode_jpeg(&image()).unwrap();
+            let fields = vec![
+                field(SOFTWARE, "screenshot"),
+                field("Ticket", "BUG-42 <a&b>"),
+            ];
+            let once = embed(&jpeg, &fields).unwrap();
+            let twice = embed(&once, &fields).unwrap();
+            assert_eq!(once, twice);
+            let segments = segments(&twice);
+            let app1: Vec<&Vec<u8>> = segments
+                .iter()
+                .filter(|s| s.0 == 0xe1)
+                .map(|s| &s.1)
+                .collect();
+            assert_eq!(segments[0].0, 0xe0);
+            assert_eq!((segments[1].0, segments[2].0), (0xe1, 0xe1));
+            assert_eq!(app1.len(), 2);
+            assert!(app1[0].starts_with(EXIF_HEADER));
+            assert!(app1[1].starts_with(XMP_HEADER));
+            let xmp = String::from_utf8(app1[1][XMP_HEADER.len()..].to_vec()).unwrap();
+            assert!(xmp.contains("<xmp:CreatorTool>screenshot</xmp:CreatorTool>"));
+            assert!(xmp.contains("<screenshot:value>BUG-42 &lt;a&amp;b&gt;</screenshot:value>"));
+            // the image data is the same
+            let original = image::load_from_memory(&jpeg).unwrap().to_rgba8();
+            assert_eq!(
+                image::load_from_memory(&twice).unwrap().to_rgba8(),
+                original
+            );
+        }
+
+        #[test]
+        fn jpeg_bad_segment_is_an_error() {
+            let mut jpeg = encode_jpeg(&image()).unwrap();
+            // a length below 2 can not hold itself
+            jpeg[4] = 0;
+            jpeg[5] = 1;
+            assert!(embed(&jpeg, &[field(SOFTWARE, "a")]).is_err());
+        }
+
+        #[test]
+        fn fields_leave_the_host_out_unless_asked() {
+            let metadata = Metadata {
+                timestamp: 0,
+                screen: 1,
+                mode: "selection".to_string(),
+                width: 4,
+                height: 3,
+                window_title: String::new(),
+            };
+            let without = fields(&metadata, " ", " a, ,b ", false, &[]);
+            assert!(without
+                .iter()
+                .all(|(k, _)| k != HOSTNAME && k != TIME && k != DESCRIPTION));
+            assert!(without.contains(&field(TAGS, "a, b")));
+            assert!(without.contains(&field("Resolution", "4x3")));
+            let with = fields(&metadata, "", "", true, &[field("Mode", "window")]);
+            assert!(with.iter().any(|(k, _)| k == HOSTNAME));
+            assert!(with.contains(&field("Mode", "window")));
+            assert!(!with.contains(&field("Mode", "selection")));
+        }
+
+        #[test]
+        fn parse_field_checks_the_key() {
+            assert_eq!(parse_field(" Ticket =a=b").unwrap(), field("Ticket", "a=b"));
+            assert!(parse_field("no value").is_err());
+            assert!(parse_field("=value").is_err());
+            assert!(parse_field("chiave è=value").is_err());
+            assert!(parse_field(&format!("{}=v", "k".repeat(80))).is_err());
+        }
+    }
+}
@@ -0,0 +1,480 @@
+pub mod optimize {
+    use crate::error::error::{write_file, AppError};
+    use crate::metadata::metadata::embed;
+    use image::{DynamicImage, RgbaImage};
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::path::Path;
+    use std::sync::mpsc;
+
+    // how much time is spent making the saved png smaller, without losing pixels
+    #[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub enum Optimization {
+        Off,
+        Fast,
+        Balanced,
+        Max, // the best zlib level with every filter, noticeably slower on big captures
+    }
+
+    impl Optimization {
+        pub fn all() -> Vec<Optimization> {
+            vec![
+                Optimization::Off,
+                Optimization::Fast,
+                Optimization::Balanced,
+                Optimization::Max,
+            ]
+        }
+    }
+
+    impl fmt::Display for Optimization {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Optimization::Off => write!(f, "Off"),
+                Optimization::Fast => write!(f, "Fast"),
+                Optimization::Balanced => write!(f, "Balanced"),
+                Optimization::Max => write!(f, "Every filter"),
+            }
+        }
+    }
+
+    // the pixels in the smallest png layout that holds them
+    enum Layout {
+        Palette(Vec<[u8; 4]>, Vec<u8>), // the colours and the index of each pixel
+        Gray(Vec<u8>),
+        GrayAlpha(Vec<u8>),
+        Rgb(Vec<u8>),
+        Rgba(Vec<u8>),
+    }
+
+    // buffer is a png; the result has the same pixels and no metadata chunks,
+    // other formats and 16 bit images are left as they are
+    pub fn optimize(buffer: &[u8], level: Optimization) -> Result<Vec<u8>, AppError> {
+        if level == Optimization::Off
+            || !matches!(image::guess_format(buffer), Ok(image::ImageFormat::Png))
+        {
+            return Ok(buffer.to_vec());
+        }
+        let image = image::load_from_memory(buffer).map_err(|e| AppError::Image(e.to_string()))?;
+        let image = match image {
+            DynamicImage::ImageRgba8(image) => image,
+            DynamicImage::ImageRgb8(_)
+            | DynamicImage::ImageLuma8(_)
+            | DynamicImage::ImageLumaA8(_) => image.to_rgba8(),
+            _ => return Ok(buffer.to_vec()),
+        };
+        let (width, height) = image.dimensions();
+        let layout = layout(&image);
+        let (compression, filters) = match level {
+            Optimization::Fast => (png::Compression::Fast, vec![None]),
+            Optimization::Balanced => (png::Compression::Best, vec![None]),
+            _ => (
+                png::Compression::Best,
+                vec![
+                    None,
+                    Some(png::FilterType::NoFilter),
+                    Some(png::FilterType::Sub),
+                    Some(png::FilterType::Up),
+                    Some(png::FilterType::Avg),
+                    Some(png::FilterType::Paeth),
+                ],
+            ),
+        };
+        // each filter is compressed on its own thread, the smallest result wins
+        let results: Vec<Result<Vec<u8>, AppError>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = filters
+                .into_iter()
+                .map(|filter| {
+                    let layout = &layout;
+                    scope.spawn(move || encode(layout, width, height, compression, filter))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle.join().unwrap_or_else(|_| {
+                        Err(AppError::Image("the png encoder panicked".to_string()))
+                    })
+                })
+                .collect()
+        });
+        let mut smallest = buffer.to_vec();
+        for result in results {
+            let optimized = result?;
+            if optimized.len() < smallest.len() {
+                smallest = optimized;
+            }
+        }
+        Ok(smallest)
+    }
+
+    fn layout(image: &RgbaImage) -> Layout {
+        let pixels = image.pixels().map(|p| p.0);
+        let opaque = image.pixels().all(|p| p[3] == 255);
+        let gray = image.pixels().all(|p| p[0] == p[1] && p[1] == p[2]);
+        let mut colours: HashMap<[u8; 4], u8> = HashMap::new();
+        for pixel in image.pixels() {
+            if colours.len() > 256 {
+                break;
+            }
+            let next = colours.len();
+            colours.entry(pixel.0).or_insert(next as u8);
+        }
+        if colours.len() <= 256 {
+            // the transparent colours first, so the alpha table can stop at the last of them
+            let mut palette: Vec<[u8; 4]> = colours.keys().copied().collect();
+            palette.sort_by_key(|c| (c[3] == 255, *c));
+            let index: HashMap<[u8; 4], u8> = palette
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (*c, i as u8))
+                .collect();
+            return Layout::Palette(palette, pixels.map(|p| index[&p]).collect());
+        }
+        match (gray, opaque) {
+            (true, true) => Layout::Gray(pixels.map(|p| p[0]).collect()),
+            (true, false) => Layout::GrayAlpha(pixels.flat_map(|p| [p[0], p[3]]).collect()),
+            (false, true) => Layout::Rgb(pixels.flat_map(|p| [p[0], p[1], p[2]]).collect()),
+            (false, false) => Layout::Rgba(image.as_raw().clone()),
+        }
+    }
+
+    // filter None lets the encoder choose the filter of each row
+    fn encode(
+        layout: &Layout,
+        width: u32,
+        height: u32,
+        compression: png::Compression,
+        filter: Option<png::FilterType>,
+    ) -> Result<Vec<u8>, AppError> {
+        let mut buffer = Vec::new();
+        let mut encoder = png::Encoder::new(&mut buffer, width, height);
+        encoder.set_compression(compression);
+        match filter {
+            Some(filter) => encoder.set_filter(filter),
+            None => encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive),
+        }
+        let packed;
+        let data = match layout {
+            Layout::Palette(palette, indices) => {
+                let depth = match palette.len() {
+                    0..=2 => png::BitDepth::One,
+                    3..=4 => png::BitDepth::Two,
+                    5..=16 => png::BitDepth::Four,
+                    _ => png::BitDepth::Eight,
+                };
+                encoder.set_color(png::ColorType::Indexed);
+                encoder.set_depth(depth);
+                let rgb: Vec<u8> = palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect();
+                encoder.set_palette(rgb);
+                let trns: Vec<u8> = palette
+                    .iter()
+                    .take_while(|c| c[3] < 255)
+                    .map(|c| c[3])
+                    .collect();
+                if !trns.is_empty() {
+                    encoder.set_trns(trns);
+                }
+                packed = pack(indices, width as usize, depth as usize);
+                &packed
+            }
+            Layout::Gray(data) => {
+                encoder.set_color(png::ColorType::Grayscale);
+                data
+            }
+            Layout::GrayAlpha(data) => {
+                encoder.set_color(png::ColorType::GrayscaleAlpha);
+                data
+            }
+            Layout::Rgb(data) => {
+                encoder.set_color(png::ColorType::Rgb);
+                data
+            }
+            Layout::Rgba(data) => {
+                encoder.set_color(png::ColorType::Rgba);
+                data
+            }
+        };
+        let error = |e: png::EncodingError| AppError::Image(e.to_string());
+        let mut writer = encoder.write_header().map_err(error)?;
+        writer.write_image_data(data).map_err(error)?;
+        writer.finish().map_err(error)?;
+        Ok(buffer)
+    }
+
+    // several indices in a byte, every row starts on a new byte
+    fn pack(indices: &[u8], width: usize, bits: usize) -> Vec<u8> {
+        if bits == 8 {
+            return indices.to_vec();
+        }
+        let per_byte = 8 / bits;
+        let row_bytes = width.div_ceil(per_byte);
+        let mut packed = Vec::with_capacity(row_bytes * indices.len() / width.max(1));
+        for row in indices.chunks(width.max(1)) {
+            for group in row.chunks(per_byte) {
+                let mut byte = 0u8;
+                for (i, index) in group.iter().enumerate() {
+                    byte |= index << (8 - bits * (i + 1));
+                }
+                packed.push(byte);
+            }
+        }
+        packed
+    }
+
+    pub fn size_text(bytes: usize) -> String {
+        match bytes {
+            0..=1023 => format!("{} B", bytes),
+            1024..=1048575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+            _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+        }
+    }
+
+    pub fn optimized_message(path: &Path, before: usize, after: usize) -> String {
+        format!(
+            "Optimized {} ({} → {}, -{}%)",
+            path.display(),
+            size_text(before),
+            size_text(after),
+            (before - after.min(before)) * 100 / before.max(1)
+        )
+    }
+
+    // the optimization runs on its own thread, so saving never blocks the ui:
+    // the png is written at once as it is and replaced when the smaller one is ready
+    pub struct Writer {
+        sender: mpsc::Sender<Result<Option<String>, AppError>>,
+        receiver: mpsc::Receiver<Result<Option<String>, AppError>>,
+        pending: usize,
+    }
+
+    impl Writer {
+        pub fn new() -> Self {
+            let (sender, receiver) = mpsc::channel();
+            Writer {
+                sender,
+                receiver,
+                pending: 0,
+            }
+        }
+
+        pub fn write(
+            &mut self,
+            path: &Path,
+            buffer: &[u8],
+            level: Optimization,
+            fields: Vec<(String, String)>,
+        ) -> Result<(), AppError> {
+            write_file(path, &embed(buffer, &fields)?)?;
+            if level == Optimization::Off {
+                return Ok(());
+            }
+            let sender = self.sender.clone();
+            let path = path.to_path_buf();
+            let buffer = buffer.to_vec();
+            std::thread::spawn(move || {
+                let _ = sender.send(replace(&path, &buffer, level, &fields));
+            });
+            self.pending += 1;
+            Ok(())
+        }
+
+        pub fn is_busy(&self) -> bool {
+            self.pending > 0
+        }
+
+        // the optimizations finished since the last call, with the message to show if the file shrank
+        pub fn finished(&mut self) -> Vec<Result<Option<String>, AppError>> {
+            let results: Vec<_> = self.receiver.try_iter().collect();
+            self.pending -= results.len();
+            results
+        }
+    }
+
+    // the smaller file goes next to the saved one and takes its place in a single rename
+    fn replace(
+        path: &Path,
+        buffer: &[u8],
+        level: Optimization,
+        fields: &[(String, String)],
+    ) -> Result<Option<String>, AppError> {
+        // the sizes are the ones of the files, metadata included
+        let original = embed(buffer, fields)?;
+        let optimized = embed(&optimize(buffer, level)?, fields)?;
+        if optimized.len() >= original.len() {
+            return Ok(None);
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = path.with_file_name(format!(".{}.tmp", name));
+        write_file(&temp, &optimized)?;
+        std::fs::rename(&temp, path).map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            AppError::Save(path.to_path_buf(), e.to_string())
+        })?;
+        Ok(Some(optimized_message(
+            path,
+            original.len(),
+            optimized.len(),
+        )))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::io::Cursor;
+
+        fn png(image: &RgbaImage) -> Vec<u8> {
+            let mut buffer = Vec::new();
+            DynamicImage::ImageRgba8(image.clone())
+                .write_to(&mut Cursor::new(&mut buffer), image::ImageOutputFormat::Png)
+                .unwrap();
+            buffer
+        }
+
+        // the png crate checks the crc of every chunk while decoding
+        fn decode(buffer: &[u8]) -> (png::ColorType, png::BitDepth, RgbaImage) {
+            let decoder = png::Decoder::new(Cursor::new(buffer));
+            let reader = decoder.read_info().unwrap();
+            let info = reader.info();
+            let kind = (info.color_type, info.bit_depth);
+            let image = image::load_from_memory(buffer).unwrap().to_rgba8();
+            (kind.0, kind.1, image)
+        }
+
+        // count colours in stripes, one colour changing every pixel
+        fn colours(width: u32, height: u32, count: u32, alpha: bool) -> RgbaImage {
+            RgbaImage::from_fn(width, height, |x, y| {
+                let i = (x + y * width) % count;
+                let a = if alpha && i == 0 { 0 } else { 255 };
+                image::Rgba([(i * 37) as u8, (i * 91) as u8, (i * 13 + i / 256) as u8, a])
+            })
+        }
+
+        #[test]
+        fn pack_puts_the_first_pixel_in_the_high_bits() {
+            assert_eq!(
+                pack(&[1, 0, 1, 1, 0, 0, 0, 1, 1], 9, 1),
+                vec![0b1011_0001, 0b1000_0000]
+            );
+            assert_eq!(pack(&[3, 2, 1], 3, 2), vec![0b1110_0100]);
+            assert_eq!(pack(&[15, 1, 2], 3, 4), vec![0xf1, 0x20]);
+            assert_eq!(pack(&[7, 200], 2, 8), vec![7, 200]);
+        }
+
+        #[test]
+        fn pack_starts_every_row_on_a_new_byte() {
+            let indices = [1, 1, 1, 0, 0, 0];
+            assert_eq!(pack(&indices, 3, 1), vec![0b1110_0000, 0]);
+            assert_eq!(pack(&indices, 3, 4), vec![0x11, 0x10, 0, 0]);
+        }
+
+        #[test]
+        fn few_colours_become_a_palette_with_the_smallest_depth() {
+            for (count, depth) in [
+                (2, png::BitDepth::One),
+                (4, png::BitDepth::Two),
+                (16, png::BitDepth::Four),
+                (200, png::BitDepth::Eight),
+            ] {
+                let image = colours(37, 11, count, false);
+                let optimized = optimize(&png(&image), Optimization::Fast).unwrap();
+                let (color, bits, decoded) = decode(&optimized);
+                assert_eq!((color, bits), (png::ColorType::Indexed, depth));
+                assert_eq!(decoded, image);
+            }
+        }
+
+        #[test]
+        fn transparent_colours_keep_their_alpha() {
+            let image = colours(20, 20, 5, true);
+            let optimized = optimize(&png(&image), Optimization::Balanced).unwrap();
+            let (color, _, decoded) = decode(&optimized);
+            assert_eq!(color, png::ColorType::Indexed);
+            assert_eq!(decoded, image);
+        }
+
+        #[test]
+        fn many_colours_keep_the_smallest_colour_type() {
+            // an opaque gray image never has more than 256 colours, with alpha it can
+            let gray = RgbaImage::from_fn(30, 30, |x, y| {
+                let v = (x * 8) as u8;
+                image::Rgba([v, v, v, 255 - y as u8])
+            });
+            let rgb = RgbaImage::from_fn(30, 30, |x, y| {
+                image::Rgba([(x * 8) as u8, (y * 8) as u8, (x * y) as u8, 255])
+            });
+            let mut rgba = rgb.clone();
+            rgba.put_pixel(0, 0, image::Rgba([1, 2, 3, 4]));
+            for (image, expected) in [
+                (gray, png::ColorType::GrayscaleAlpha),
+                (rgb, png::ColorType::Rgb),
+                (rgba, png::ColorType::Rgba),
+            ] {
+                // the encoder of image writes rgba, so anything smaller wins
+                let optimized = optimize(&png(&image), Optimization::Fast).unwrap();
+                let (color, _, decoded) = decode(&optimized);
+                assert_eq!(color, expected);
+                assert_eq!(decoded, image);
+            }
+        }
+
+        #[test]
+        fn every_level_keeps_the_pixels() {
+            let image = RgbaImage::from_fn(64, 48, |x, y| {
+                image::Rgba([
+                    (x * 4) as u8,
+                    (y * 5) as u8,
+                    ((x + y) * 3) as u8,
+                    255 - x as u8,
+                ])
+            });
+            let original = png(&image);
+            for level in Optimization::all() {
+                let optimized = optimize(&original, level).unwrap();
+                assert!(optimized.len() <= original.len());
+                assert_eq!(decode(&optimized).2, image);
+            }
+            assert_eq!(optimize(&original, Optimization::Off).unwrap(), original);
+        }
+
+        #[test]
+        fn other_formats_are_left_alone() {
+            let not_png = b"GIF89a not really".to_vec();
+            assert_eq!(optimize(&not_png, Optimization::Max).unwrap(), not_png);
+        }
+
+        #[test]
+        fn writer_replaces_the_file_with_the_smaller_one() {
+            let image = colours(200, 100, 3, false);
+            let original = png(&image);
+            let path =
+                std::env::temp_dir().join(format!("optimize-test-{}.png", std::process::id()));
+            let mut writer = Writer::new();
+            writer
+                .write(&path, &original, Optimization::Balanced, Vec::new())
+                .unwrap();
+            assert!(writer.is_busy());
+            let mut results = Vec::new();
+            while results.is_empty() {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                results = writer.finished();
+            }
+            let written = std::fs::read(&path);
+            let _ = std::fs::remove_file(&path);
+            assert!(!writer.is_busy());
+            let message = results.remove(0).unwrap().unwrap();
+            assert!(message.starts_with(&format!("Optimized {}", path.display())));
+            let written = written.unwrap();
+            assert!(written.len() < original.len());
+            assert_eq!(decode(&written).2, image);
+        }
+
+        #[test]
+        fn size_text_units() {
+            assert_eq!(size_text(1023), "1023 B");
+            assert_eq!(size_text(1536), "1.5 KB");
+            assert_eq!(size_text(3 * 1048576), "3.0 MB");
+        }
+    }
+}
@@ -0,0 +1,187 @@
+pub mod pipeline {
+    use crate::error::error::AppError;
+    use serde::{Deserialize, Serialize};
+    use std::fmt;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    #[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub enum PostAction {
+        Save,
+        Copy,
+        OpenInEditor,
+        RunCommand,
+        Notify,
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct PostStep {
+        pub action: PostAction,
+        pub enabled: bool,
+    }
+
+    // the actions run in order after every capture, without asking anything
+    pub struct Pipeline {
+        steps: Vec<PostStep>,
+        pub editor: String,  // empty -> the default application of the system
+        pub command: String, // {path} is replaced by the file of the capture
+    }
+
+    impl PostAction {
+        pub fn all() -> Vec<PostAction> {
+            vec![
+                PostAction::Save,
+                PostAction::Copy,
+                PostAction::OpenInEditor,
+                PostAction::RunCommand,
+                PostAction::Notify,
+            ]
+        }
+
+        // these actions need the capture in a file
+        pub fn needs_file(self) -> bool {
+            matches!(self, PostAction::OpenInEditor | PostAction::RunCommand)
+        }
+    }
+
+    impl fmt::Display for PostAction {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                PostAction::Save => write!(f, "Save to the default location"),
+                PostAction::Copy => write!(f, "Copy to the clipboard"),
+                PostAction::OpenInEditor => write!(f, "Open in the editor"),
+                PostAction::RunCommand => write!(f, "Run a shell command"),
+                PostAction::Notify => write!(f, "Show a notification"),
+            }
+        }
+    }
+
+    impl Pipeline {
+        // actions missing from a saved configuration are added, disabled, at the end
+        pub fn new(mut steps: Vec<PostStep>, editor: String, command: String) -> Self {
+            let mut seen = Vec::new();
+            steps.retain(|s| {
+                let first = !seen.contains(&s.action);
+                seen.push(s.action);
+                first
+            });
+            for action in PostAction::all() {
+                if !seen.contains(&action) {
+                    steps.push(PostStep {
+                        action,
+                        enabled: false,
+                    });
+                }
+            }
+            Pipeline {
+                steps,
+                editor,
+                command,
+            }
+        }
+
+        pub fn steps(&self) -> &Vec<PostStep> {
+            &self.steps
+        }
+
+        pub fn steps_mut(&mut self) -> &mut Vec<PostStep> {
+            &mut self.steps
+        }
+
+        pub fn enabled(&self) -> Vec<PostAction> {
+            self.steps
+                .iter()
+                .filter(|s| s.enabled)
+                .map(|s| s.action)
+                .collect()
+        }
+
+        pub fn move_up(&mut self, index: usize) {
+            if index > 0 && index < self.steps.len() {
+                self.steps.swap(index, index - 1);
+            }
+        }
+
+        pub fn move_down(&mut self, index: usize) {
+            if index + 1 < self.steps.len() {
+                self.steps.swap(index, index + 1);
+            }
+        }
+
+        pub fn open_in_editor(&self, path: &Path) -> Result<(), AppError> {
+            let editor = self.editor.trim();
+            let result = if editor.is_empty() {
+                open_with_system(path)
+            } else {
+                shell(editor, path)
+            };
+            result.map_err(|e| AppError::Command(format!("cannot open the editor: {}", e)))
+        }
+
+        pub fn run_command(&self, path: &Path) -> Result<(), AppError> {
+            if self.command.trim().is_empty() {
+                return Err(AppError::Command("the command is empty".to_string()));
+            }
+            shell(self.command.trim(), path).map_err(|e| AppError::Command(e.to_string()))
+        }
+    }
+
+    // a file for the editor or the command when the capture has not been saved
+    pub fn temp_file(buffer: &[u8]) -> Result<PathBuf, AppError> {
+        let name = chrono::Local::now().format("screenshot-%Y%m%d-%H%M%S%.3f.png");
+        let path = std::env::temp_dir().join(name.to_string());
+        fs::write(&path, buffer).map_err(|e| AppError::Save(path.clone(), e.to_string()))?;
+        Ok(path)
+    }
+
+    // replaces {path}, or adds the path at the end of the command; the file name never
+    // becomes part of the command, quotes, $() or backticks in it are not run
+    fn with_path(command: &str) -> String {
+        let variable = if cfg!(target_os = "windows") {
+            "\"%SCREENSHOT_PATH%\""
+        } else {
+            "\"$1\""
+        };
+        // {path} already in quotes would end up outside of them
+        let command = command
+            .replace("\"{path}\"", "{path}")
+            .replace("'{path}'", "{path}");
+        if command.contains("{path}") {
+            command.replace("{path}", variable)
+        } else {
+            format!("{} {}", command, variable)
+        }
+    }
+
+    // sh gets the path as $1, cmd as a variable that is expanded only once
+    fn shell(command: &str, path: &Path) -> std::io::Result<()> {
+        let command = with_path(command);
+        if cfg!(target_os = "windows") {
+            spawn(
+                Command::new("cmd")
+                    .args(["/C", &command])
+                    .env("SCREENSHOT_PATH", path),
+            )
+        } else {
+            spawn(Command::new("sh").args(["-c", &command, "sh"]).arg(path))
+        }
+    }
+
+    fn open_with_system(path: &Path) -> std::io::Result<()> {
+        if cfg!(target_os = "windows") {
+            spawn(Command::new("cmd").args(["/C", "start", ""]).arg(path))
+        } else if cfg!(target_os = "macos") {
+            spawn(Command::new("open").arg(path))
+        } else {
+            spawn(Command::new("xdg-open").arg(path))
+        }
+    }
+
+    // the program runs in the background, the ui doesn't wait for it
+    fn spawn(command: &mut Command) -> std::io::Result<()> {
+        let mut child = command.spawn()?;
+        std::thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+pub mod project {
+    use crate::error::error::AppError;
+    use serde::{Deserialize, Serialize};
+    use std::fs;
+    use std::io::{Cursor, Read, Write};
+    use std::path::Path;
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+    pub const EXTENSION: &str = "rgrab";
+    // increased every time the document changes in a way older versions can't read
+    // 2: magnifiers
+    pub const VERSION: u32 = 2;
+
+    const IMAGE_ENTRY: &str = "capture.png";
+    const DOCUMENT_ENTRY: &str = "project.json";
+
+    // a zip with the original capture and the annotations, which stay editable
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Project {
+        pub image: Vec<u8>,
+        pub document: Document,
+    }
+
+    // coordinates and widths are in pixels of the original capture
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Document {
+        pub version: u32,
+        pub metadata: Metadata,
+        pub annotations: Annotations,
+        pub crop: Option<Rect>,
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Metadata {
+        pub timestamp: i64, // milliseconds since the epoch
+        pub screen: usize,
+        pub mode: String,
+        pub width: u32,
+        pub height: u32,
+        pub window_title: String,
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Annotations {
+        pub pen: Vec<Shape>,
+        pub line: Vec<Shape>,
+        pub arrow: Vec<Shape>,
+        pub rect: Vec<Shape>,
+        pub circle: Vec<Shape>,
+        pub text: Vec<Text>,
+        pub magnifier: Vec<Magnifier>,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Point {
+        pub x: f32,
+        pub y: f32,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Style {
+        pub width: f32,
+        pub color: [u8; 4], // rgba, premultiplied like egui
+    }
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Shape {
+        pub points: Vec<Point>,
+        pub style: Style,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Text {
+        pub position: Point,
+        pub text: String,
+        pub style: Style,
+    }
+
+    // a magnified copy of source drawn in inset, linked to it by a line
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Magnifier {
+        pub source: Rect,
+        pub inset: Rect,
+        pub circle: bool, // the rectangles are squares then
+        pub style: Style,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Rect {
+        pub x: f32,
+        pub y: f32,
+        pub width: f32,
+        pub height: f32,
+    }
+
+    impl Document {
+        pub fn new(metadata: Metadata, annotations: Annotations, crop: Option<Rect>) -> Self {
+            Document {
+                version: VERSION,
+                metadata,
+                annotations,
+                crop,
+            }
+        }
+    }
+
+    pub fn write(project: &Project) -> Result<Vec<u8>, AppError> {
+        let error = |e: String| AppError::Project(e);
+        let json =
+            serde_json::to_string_pretty(&project.document).map_err(|e| error(e.to_string()))?;
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        // the png is already compressed
+        zip.start_file(
+            IMAGE_ENTRY,
+            FileOptions::default().compression_method(CompressionMethod::Stored),
+        )
+        .map_err(|e| error(e.to_string()))?;
+        zip.write_all(&project.image)
+            .map_err(|e| error(e.to_string()))?;
+        zip.start_file(DOCUMENT_ENTRY, FileOptions::default())
+            .map_err(|e| error(e.to_string()))?;
+        zip.write_all(json.as_bytes())
+            .map_err(|e| error(e.to_string()))?;
+        let cursor = zip.finish().map_err(|e| error(e.to_string()))?;
+        Ok(cursor.into_inner())
+    }
+
+    pub fn read(bytes: &[u8]) -> Result<Project, AppError> {
+        let error = |e: String| AppError::Project(e);
+        let mut zip = ZipArchive::new(Cursor::new(bytes)).map_err(|e| error(e.to_string()))?;
+        let mut json = String::new();
+        zip.by_name(DOCUMENT_ENTRY)
+            .map_err(|e| error(format!("{}: {}", DOCUMENT_ENTRY, e)))?
+            .read_to_string(&mut json)
+            .map_err(|e| error(e.to_string()))?;
+        let document = parse_document(&json)?;
+        let mut image = Vec::new();
+        zip.by_name(IMAGE_ENTRY)
+            .map_err(|e| error(format!("{}: {}", IMAGE_ENTRY, e)))?
+            .read_to_end(&mut image)
+            .map_err(|e| error(e.to_string()))?;
+        Ok(Project { image, document })
+    }
+
+    // the version is checked before the rest, so a newer file gets a clear message
+    fn parse_document(json: &str) -> Result<Document, AppError> {
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+        }
+        let header: Header =
+            serde_json::from_str(json).map_err(|e| AppError::Project(e.to_string()))?;
+        match header.version {
+            // the fields added after version 1 have defaults
+            1 | 2 => serde_json::from_str(json).map_err(|e| AppError::Project(e.to_string())),
+            version => Err(AppError::Project(format!(
+                "version {} is not supported, the newest known is {}",
+                version, VERSION
+            ))),
+        }
+    }
+
+    pub fn save(path: &Path, project: &Project) -> Result<(), AppError> {
+        let bytes = write(project)?;
+        fs::write(path, bytes).map_err(|e| AppError::Save(path.to_path_buf(), e.to_string()))
+    }
+
+    pub fn open(path: &Path) -> Result<Project, AppError> {
+        let bytes =
+            fs::read(path).map_err(|e| AppError::Project(format!("{}: {}", path.display(), e)))?;
+        read(&bytes)
+    }
+
+    pub fn is_project(path: &Path) -> bool {
+        path.extension().is_some_and(|e| e == EXTENSION)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn png() -> Vec<u8> {
+            let image = image::RgbaImage::from_pixel(4, 3, image::Rgba([10, 20, 30, 255]));
+            let mut buffer = Vec::new();
+            image::DynamicImage::ImageRgba8(image)
+                .write_to(&mut Cursor::new(&mut buffer), image::ImageOutputFormat::Png)
+                .unwrap();
+            buffer
+        }
+
+        fn style(width: f32) -> Style {
+            Style {
+                width,
+                color: [255, 0, 0, 255],
+            }
+        }
+
+        fn shape(points: &[(f32, f32)], width: f32) -> Shape {
+            Shape {
+                points: points.iter().map(|&(x, y)| Point { x, y }).collect(),
+                style: style(width),
+            }
+        }
+
+        fn project() -> Project {
+            let annotations = Annotations {
+                pen: vec![shape(&[(0.0, 0.0), (1.5, 1.0), (2.0, 2.5)], 1.0)],
+                line: vec![shape(&[(0.0, 0.0), (3.0, 2.0)], 2.0)],
+                arrow: vec![shape(&[(1.0, 1.0), (3.0, 1.0)], 3.0)],
+                rect: vec![
+                    shape(&[(0.0, 0.0), (2.0, 2.0)], 0.5),
+                    shape(&[(1.0, 0.0), (3.0, 2.0)], 0.5),
+                ],
+                circle: vec![shape(&[(2.0, 1.5), (3.0, 1.5)], 1.0)],
+                text: vec![Text {
+                    position: Point { x: 0.5, y: 0.5 },
+                    text: "Hello \"world\"\nsecond line ✓".to_string(),
+                    style: style(1.25),
+                }],
+                magnifier: vec![Magnifier {
+                    source: Rect {
+                        x: 0.0,
+                        y: 0.0,
+                        width: 1.0,
+                        height: 1.0,
+                    },
+                    inset: Rect {
+                        x: 1.5,
+                        y: 0.5,
+                        width: 2.5,
+                        height: 2.5,
+                    },
+                    circle: true,
+                    style: style(0.5),
+                }],
+            };
+            let metadata = Metadata {
+                timestamp: 1_697_800_000_123,
+                screen: 1,
+                mode: "selection".to_string(),
+                width: 4,
+                height: 3,
+                window_title: "Terminal".to_string(),
+            };
+            let crop = Some(Rect {
+                x: 1.0,
+                y: 0.0,
+                width: 2.0,
+                height: 3.0,
+            });
+            Project {
+                image: png(),
+                document: Document::new(metadata, annotations, crop),
+            }
+        }
+
+        #[test]
+        fn round_trip() {
+            let original = project();
+            let bytes = write(&original).unwrap();
+            assert_eq!(read(&bytes).unwrap(), original);
+        }
+
+        #[test]
+        fn round_trip_empty() {
+            let original = Project {
+                image: png(),
+                document: Document::new(Metadata::default(), Annotations::default(), None),
+            };
+            let read_back = read(&write(&original).unwrap()).unwrap();
+            assert_eq!(read_back, original);
+            assert_eq!(read_back.document.version, VERSION);
+        }
+
+        #[test]
+        fn round_trip_file() {
+            let original = project();
+            let path = std::env::temp_dir().join(format!(
+                "project-test-{}.{}",
+                std::process::id(),
+                EXTENSION
+            ));
+            save(&path, &original).unwrap();
+            let read_back = open(&path);
+            let _ = fs::remove_file(&path);
+            assert!(is_project(&path));
+            assert_eq!(read_back.unwrap(), original);
+        }
+
+        #[test]
+        fn image_is_untouched() {
+            let original = project();
+            let read_back = read(&write(&original).unwrap()).unwrap();
+            let image = image::load_from_memory(&read_back.image).unwrap();
+            assert_eq!((image.width(), image.height()), (4, 3));
+        }
+
+        #[test]
+        fn missing_fields_get_defaults() {
+            let json =
+                r#"{"version": 1, "metadata": {"width": 4}, "annotations": {}, "crop": null}"#;
+            let document = parse_document(json).unwrap();
+            assert_eq!(document.metadata.width, 4);
+            assert_eq!(document.annotations, Annotations::default());
+        }
+
+        #[test]
+        fn version_one_is_read() {
+            let json = r#"{"version": 1, "metadata": {}, "annotations": {"pen": []}, "crop": null}"#;
+            let document = parse_document(json).unwrap();
+            assert_eq!(document.version, 1);
+            assert!(document.annotations.magnifier.is_empty());
+        }
+
+        #[test]
+        fn newer_version_is_rejected() {
+            let mut original = project();
+            original.document.version = VERSION + 1;
+            let error = read(&write(&original).unwrap()).unwrap_err();
+            assert!(error.to_string().contains("not supported"));
+        }
+
+        #[test]
+        fn not_a_project() {
+            assert!(read(&png()).is_err());
+            let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+            zip.start_file(IMAGE_ENTRY, FileOptions::default()).unwrap();
+            zip.write_all(&png()).unwrap();
+            let bytes = zip.finish().unwrap().into_inner();
+            assert!(read(&bytes).is_err());
+        }
+    }
+}
@@ -0,0 +1,201 @@
+pub mod recorder {
+    use crate::RectangleCrop;
+    use image::codecs::gif::{GifEncoder, Repeat};
+    use image::{Delay, Frame, RgbaImage};
+    use screenshots::Screen;
+    use std::fs::{self, File};
+    use std::io::{BufWriter, Write};
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::JoinHandle;
+    use std::time::{Duration, Instant};
+
+    pub const MAX_FRAMES: usize = 3000;
+
+    pub struct Recording {
+        frames: Vec<Vec<u8>>, // png encoded frames
+        delays: Vec<Duration>,
+    }
+
+    pub struct Recorder {
+        stop: Arc<AtomicBool>,
+        handle: JoinHandle<Recording>,
+        started: Instant,
+    }
+
+    impl Recorder {
+        pub fn start(screen: Screen, rect: Option<RectangleCrop>, fps: u32) -> Self {
+            let stop = Arc::new(AtomicBool::new(false));
+            let stop_thread = stop.clone();
+            let interval = Duration::from_secs_f64(1.0 / fps.max(1) as f64);
+            let handle = std::thread::spawn(move || {
+                let mut recording = Recording {
+                    frames: Vec::new(),
+                    delays: Vec::new(),
+                };
+                let mut last_frame = Instant::now();
+                let mut next = Instant::now();
+                while !stop_thread.load(Ordering::Relaxed) && recording.frames.len() < MAX_FRAMES
+                {
+                    let image = match rect {
+                        Some(rect) => screen.capture_area(
+                            rect.x_left.floor() as i32,
+                            rect.y_left.floor() as i32,
+                            rect.width.floor() as u32,
+                            rect.height.floor() as u32,
+                        ),
+                        None => screen.capture(),
+                    };
+                    if let Ok(Ok(png)) = image.map(|image| image.to_png(None)) {
+                        let now = Instant::now();
+                        if let Some(delay) = recording.delays.last_mut() {
+                            *delay = now - last_frame;
+                        }
+                        recording.frames.push(png);
+                        recording.delays.push(interval);
+                        last_frame = now;
+                    }
+                    next += interval;
+                    let now = Instant::now();
+                    if next > now {
+                        std::thread::sleep(next - now);
+                    } else {
+                        next = now;
+                    }
+                }
+                recording
+            });
+            Recorder {
+                stop,
+                handle,
+                started: Instant::now(),
+            }
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            self.started.elapsed()
+        }
+
+        // the thread ends by itself only when MAX_FRAMES have been taken
+        pub fn is_full(&self) -> bool {
+            self.handle.is_finished()
+        }
+
+        pub fn stop(self) -> Option<Recording> {
+            self.stop.store(true, Ordering::Relaxed);
+            self.handle.join().ok()
+        }
+    }
+
+    impl Recording {
+        pub fn is_empty(&self) -> bool {
+            self.frames.is_empty()
+        }
+
+        fn frame(&self, index: usize) -> Result<RgbaImage, String> {
+            image::load_from_memory(&self.frames[index])
+                .map(|image| image.to_rgba8())
+                .map_err(|e| e.to_string())
+        }
+
+        // chooses the encoder from the extension of the file
+        pub fn encode(&self, path: &Path) -> Result<(), String> {
+            let extension = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            match extension.as_str() {
+                "gif" => self.encode_gif(path),
+                "png" | "apng" => self.encode_apng(path),
+                "webm" => self.encode_webm(path),
+                _ => Err(format!("Unsupported recording format: {}", extension)),
+            }
+        }
+
+        fn encode_gif(&self, path: &Path) -> Result<(), String> {
+            let file = File::create(path).map_err(|e| e.to_string())?;
+            // speed 1, the slowest, gives the best palette for every frame
+            let mut encoder = GifEncoder::new(BufWriter::new(file));
+            encoder
+                .set_repeat(Repeat::Infinite)
+                .map_err(|e| e.to_string())?;
+            for i in 0..self.frames.len() {
+                let frame = Frame::from_parts(
+                    self.frame(i)?,
+                    0,
+                    0,
+                    Delay::from_saturating_duration(self.delays[i]),
+                );
+                encoder.encode_frame(frame).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        }
+
+        fn encode_apng(&self, path: &Path) -> Result<(), String> {
+            let first = self.frame(0)?;
+            let file = File::create(path).map_err(|e| e.to_string())?;
+            let mut encoder = png::Encoder::new(BufWriter::new(file), first.width(), first.height());
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder
+                .set_animated(self.frames.len() as u32, 0)
+                .map_err(|e| e.to_string())?;
+            let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+            for i in 0..self.frames.len() {
+                let delay = self.delays[i].as_millis().min(u16::MAX as u128) as u16;
+                writer
+                    .set_frame_delay(delay, 1000)
+                    .map_err(|e| e.to_string())?;
+                writer
+                    .write_image_data(self.frame(i)?.as_raw())
+                    .map_err(|e| e.to_string())?;
+            }
+            writer.finish().map_err(|e| e.to_string())
+        }
+
+        // VP8 encoding is delegated to the ffmpeg executable installed on the system
+        fn encode_webm(&self, path: &Path) -> Result<(), String> {
+            let first = self.frame(0)?;
+            let total: Duration = self.delays.iter().sum();
+            let fps = self.frames.len() as f64 / total.as_secs_f64().max(0.001);
+            let mut child = Command::new("ffmpeg")
+                .args(["-y", "-f", "rawvideo", "-pix_fmt", "rgba", "-s"])
+                .arg(format!("{}x{}", first.width(), first.height()))
+                .arg("-r")
+                .arg(format!("{:.3}", fps))
+                .args(["-i", "-", "-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2"])
+                .args(["-c:v", "libvpx", "-b:v", "2M", "-pix_fmt", "yuv420p"])
+                .arg(path)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .map_err(|_| "ffmpeg not found: install it to export WebM videos".to_string())?;
+            let fed = match child.stdin.take() {
+                Some(mut stdin) => (0..self.frames.len()).try_for_each(|i| {
+                    stdin
+                        .write_all(self.frame(i)?.as_raw())
+                        .map_err(|e| e.to_string())
+                }),
+                None => Err("Cannot write to ffmpeg".to_string()),
+            };
+            // stdin is closed here, so ffmpeg sees the end of the frames
+            if let Err(error) = fed {
+                // ffmpeg must not keep running, or linger, writing a partial file
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = fs::remove_file(path);
+                return Err(error);
+            }
+            let status = child.wait().map_err(|e| e.to_string())?;
+            if status.success() {
+                Ok(())
+            } else {
+                let _ = fs::remove_file(path);
+                Err("ffmpeg failed to encode the video".to_string())
+            }
+        }
+    }
+}
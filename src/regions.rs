@@ -0,0 +1,90 @@
+pub mod regions {
+    use crate::RectangleCrop;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct Region {
+        #[serde(default)]
+        pub id: u32, // stable, the region shortcuts refer to it
+        pub name: String,
+        pub screen_no: usize,
+        pub rect: Option<RectangleCrop>, // None -> entire screen
+    }
+
+    pub struct Regions {
+        saved: Vec<Region>,
+        last: Option<Region>,
+        pending: Option<Region>,
+        pub new_name: String,
+    }
+
+    impl Regions {
+        pub fn new(mut saved: Vec<Region>) -> Self {
+            // regions saved before the ids existed get one now
+            let mut next = saved.iter().map(|r| r.id).max().unwrap_or(0);
+            for region in saved.iter_mut().filter(|r| r.id == 0) {
+                next += 1;
+                region.id = next;
+            }
+            Regions {
+                saved,
+                last: None,
+                pending: None,
+                new_name: "".to_string(),
+            }
+        }
+
+        pub fn list(&self) -> &Vec<Region> {
+            &self.saved
+        }
+
+        pub fn get(&self, id: u32) -> Option<Region> {
+            self.saved.iter().find(|r| r.id == id).cloned()
+        }
+
+        pub fn save_region(&mut self, screen_no: usize, rect: Option<RectangleCrop>) {
+            let mut name = self.new_name.trim().to_string();
+            if name.is_empty() {
+                name = format!("Region {}", self.saved.len() + 1);
+            }
+            match self.saved.iter_mut().find(|r| r.name == name) {
+                Some(region) => {
+                    region.screen_no = screen_no;
+                    region.rect = rect;
+                }
+                None => self.saved.push(Region {
+                    id: self.saved.iter().map(|r| r.id).max().unwrap_or(0) + 1,
+                    name,
+                    screen_no,
+                    rect,
+                }),
+            }
+            self.new_name = "".to_string();
+        }
+
+        pub fn delete_region(&mut self, id: u32) {
+            self.saved.retain(|r| r.id != id);
+        }
+
+        pub fn set_last(&mut self, screen_no: usize, rect: Option<RectangleCrop>) {
+            self.last = Some(Region {
+                id: 0,
+                name: "Last capture".to_string(),
+                screen_no,
+                rect,
+            });
+        }
+
+        pub fn get_last(&self) -> Option<Region> {
+            self.last.clone()
+        }
+
+        pub fn set_pending(&mut self, region: Region) {
+            self.pending = Some(region);
+        }
+
+        pub fn take_pending(&mut self) -> Option<Region> {
+            self.pending.take()
+        }
+    }
+}
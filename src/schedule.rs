@@ -0,0 +1,385 @@
+pub mod schedule {
+    use chrono::{
+        DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+        Timelike,
+    };
+
+    enum When {
+        Cron(Cron),
+        At(NaiveDateTime),
+    }
+
+    // standard 5 fields cron: minute hour day-of-month month day-of-week
+    struct Cron {
+        minutes: Vec<bool>,
+        hours: Vec<bool>,
+        days: Vec<bool>,
+        months: Vec<bool>,
+        weekdays: Vec<bool>,
+        any_day: bool,
+        any_weekday: bool,
+    }
+
+    pub struct Schedule {
+        expression: String,
+        when: When,
+        next: Option<DateTime<Local>>,
+    }
+
+    pub struct Schedules {
+        list: Vec<Schedule>,
+        pub new_expression: String,
+        pub error: Option<String>,
+    }
+
+    impl Cron {
+        fn parse(expression: &str) -> Result<Self, String> {
+            let fields: Vec<&str> = expression.split_whitespace().collect();
+            if fields.len() != 5 {
+                return Err("A cron expression needs 5 fields".to_string());
+            }
+            let mut weekdays = parse_field(fields[4], 0, 7)?;
+            if weekdays[7] {
+                weekdays[0] = true; // 7 is sunday too
+            }
+            Ok(Cron {
+                minutes: parse_field(fields[0], 0, 59)?,
+                hours: parse_field(fields[1], 0, 23)?,
+                days: parse_field(fields[2], 1, 31)?,
+                months: parse_field(fields[3], 1, 12)?,
+                weekdays,
+                any_day: fields[2] == "*",
+                any_weekday: fields[4] == "*",
+            })
+        }
+
+        fn day_matches(&self, date: NaiveDate) -> bool {
+            let day = self.days[date.day() as usize];
+            let weekday = self.weekdays[date.weekday().num_days_from_sunday() as usize];
+            // like cron, when both are restricted either of them is enough
+            match (self.any_day, self.any_weekday) {
+                (true, true) => true,
+                (false, true) => day,
+                (true, false) => weekday,
+                (false, false) => day || weekday,
+            }
+        }
+
+        // skips whole months, days and hours that cannot match, so it stays fast
+        // even when the next match is years away (29 february comes at most 8 years later)
+        fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+            let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+            let mut time = start.naive_local();
+            let last_year = time.year() + 8;
+            while time.year() <= last_year {
+                let date = time.date();
+                if !self.months[date.month() as usize] {
+                    let (year, month) = if date.month() == 12 {
+                        (date.year() + 1, 1)
+                    } else {
+                        (date.year(), date.month() + 1)
+                    };
+                    time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                } else if !self.day_matches(date) {
+                    time = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+                } else if !self.hours[time.hour() as usize] {
+                    time = date.and_hms_opt(time.hour(), 0, 0)? + Duration::hours(1);
+                } else if !self.minutes[time.minute() as usize] {
+                    time += Duration::minutes(1);
+                } else {
+                    // a time skipped by daylight saving does not exist, the search goes on
+                    match Local.from_local_datetime(&time).earliest() {
+                        Some(found) if found >= start => return Some(found),
+                        _ => time += Duration::minutes(1),
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>, String> {
+        let error = || format!("Invalid cron field: {}", field);
+        let mut allowed = vec![false; max as usize + 1];
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<usize>().map_err(|_| error())?),
+                None => (part, 1),
+            };
+            if step == 0 {
+                return Err(error());
+            }
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else if let Some((start, end)) = range.split_once('-') {
+                (
+                    start.parse::<u32>().map_err(|_| error())?,
+                    end.parse::<u32>().map_err(|_| error())?,
+                )
+            } else {
+                let value = range.parse::<u32>().map_err(|_| error())?;
+                (value, if step > 1 { max } else { value })
+            };
+            if start < min || end > max || start > end {
+                return Err(error());
+            }
+            for value in (start..=end).step_by(step) {
+                allowed[value as usize] = true;
+            }
+        }
+        Ok(allowed)
+    }
+
+    impl Schedule {
+        // accepts a cron expression, "every day at HH:MM", "every weekday at HH:MM",
+        // "at HH:MM [today|tomorrow]" and "at YYYY-MM-DD HH:MM"
+        pub fn parse(expression: &str, now: DateTime<Local>) -> Result<Self, String> {
+            let expression = expression.trim().to_lowercase();
+            let (expression, when) = if let Some(time) = expression.strip_prefix("every day at ") {
+                let time = parse_time(time)?;
+                let cron = format!("{} {} * * *", time.minute(), time.hour());
+                (cron.clone(), When::Cron(Cron::parse(&cron)?))
+            } else if let Some(time) = expression.strip_prefix("every weekday at ") {
+                let time = parse_time(time)?;
+                let cron = format!("{} {} * * 1-5", time.minute(), time.hour());
+                (cron.clone(), When::Cron(Cron::parse(&cron)?))
+            } else if let Some(rest) = expression.strip_prefix("at ") {
+                let at = parse_at(rest.trim(), now)?;
+                (format!("at {}", at.format("%Y-%m-%d %H:%M")), When::At(at))
+            } else {
+                (expression.clone(), When::Cron(Cron::parse(&expression)?))
+            };
+            let mut schedule = Schedule {
+                expression,
+                when,
+                next: None,
+            };
+            schedule.next = schedule.next_after(now);
+            Ok(schedule)
+        }
+
+        fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+            match &self.when {
+                When::Cron(cron) => cron.next_after(after),
+                When::At(at) => Local
+                    .from_local_datetime(at)
+                    .earliest()
+                    .filter(|time| *time > after),
+            }
+        }
+
+        pub fn expression(&self) -> &str {
+            &self.expression
+        }
+
+        pub fn next(&self) -> Option<DateTime<Local>> {
+            self.next
+        }
+    }
+
+    fn parse_time(text: &str) -> Result<NaiveTime, String> {
+        NaiveTime::parse_from_str(text.trim(), "%H:%M")
+            .map_err(|_| format!("Invalid time: {} (use HH:MM)", text.trim()))
+    }
+
+    fn parse_at(text: &str, now: DateTime<Local>) -> Result<NaiveDateTime, String> {
+        if let Ok(at) = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M") {
+            return Ok(at);
+        }
+        let mut words = text.split_whitespace();
+        let time = parse_time(words.next().unwrap_or(""))?;
+        let today: NaiveDate = now.date_naive();
+        let at = match words.next() {
+            Some("today") => today.and_time(time),
+            Some("tomorrow") => (today + Duration::days(1)).and_time(time),
+            None => {
+                // without a day, the next time it happens
+                if time > now.time() {
+                    today.and_time(time)
+                } else {
+                    (today + Duration::days(1)).and_time(time)
+                }
+            }
+            Some(word) => return Err(format!("Unknown day: {}", word)),
+        };
+        if at <= now.naive_local() {
+            return Err("This time has already passed".to_string());
+        }
+        Ok(at)
+    }
+
+    impl Schedules {
+        // expired or invalid schedules are dropped
+        pub fn new(expressions: Vec<String>) -> Self {
+            let now = Local::now();
+            Schedules {
+                list: expressions
+                    .iter()
+                    .filter_map(|e| Schedule::parse(e, now).ok())
+                    .filter(|s| s.next.is_some())
+                    .collect(),
+                new_expression: "".to_string(),
+                error: None,
+            }
+        }
+
+        pub fn add(&mut self) {
+            match Schedule::parse(&self.new_expression, Local::now()) {
+                Ok(schedule) => {
+                    if schedule.next.is_some() {
+                        self.list.push(schedule);
+                        self.new_expression = "".to_string();
+                        self.error = None;
+                    } else {
+                        self.error = Some("This schedule never happens".to_string());
+                    }
+                }
+                Err(error) => self.error = Some(error),
+            }
+        }
+
+        pub fn remove(&mut self, index: usize) {
+            if index < self.list.len() {
+                self.list.remove(index);
+            }
+        }
+
+        pub fn list(&self) -> &Vec<Schedule> {
+            &self.list
+        }
+
+        pub fn expressions(&self) -> Vec<String> {
+            self.list.iter().map(|s| s.expression.clone()).collect()
+        }
+
+        // true if a capture is due; due schedules move to their next time
+        pub fn take_due(&mut self, now: DateTime<Local>) -> bool {
+            let mut due = false;
+            for schedule in self.list.iter_mut() {
+                if let Some(next) = schedule.next {
+                    if next <= now {
+                        due = true;
+                        schedule.next = schedule.next_after(now);
+                    }
+                }
+            }
+            self.list.retain(|s| s.next.is_some());
+            due
+        }
+
+        pub fn next_due(&self) -> Option<DateTime<Local>> {
+            self.list.iter().filter_map(|s| s.next).min()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+            Local
+                .with_ymd_and_hms(year, month, day, hour, minute, 0)
+                .unwrap()
+        }
+
+        fn next(expression: &str, after: DateTime<Local>) -> Option<DateTime<Local>> {
+            Cron::parse(expression).unwrap().next_after(after)
+        }
+
+        fn values(field: &str, min: u32, max: u32) -> Vec<u32> {
+            let allowed = parse_field(field, min, max).unwrap();
+            (min..=max).filter(|&v| allowed[v as usize]).collect()
+        }
+
+        #[test]
+        fn fields_are_parsed() {
+            assert_eq!(values("*/15", 0, 59), vec![0, 15, 30, 45]);
+            assert_eq!(values("1-5", 0, 7), vec![1, 2, 3, 4, 5]);
+            assert_eq!(values("1,3,5", 0, 7), vec![1, 3, 5]);
+            assert_eq!(values("10-20/5", 0, 59), vec![10, 15, 20]);
+            assert_eq!(values("50/5", 0, 59), vec![50, 55]);
+            assert_eq!(values("1-2,30", 1, 31), vec![1, 2, 30]);
+            assert_eq!(values("*", 1, 12).len(), 12);
+            for field in ["60", "5-1", "*/0", "a", "", "1-", "0"] {
+                assert!(parse_field(field, 1, 59).is_err(), "{}", field);
+            }
+        }
+
+        #[test]
+        fn expressions_are_parsed() {
+            assert!(Cron::parse("0 0 * *").is_err());
+            assert!(Cron::parse("0 0 * * * *").is_err());
+            assert!(Cron::parse("0 24 * * *").is_err());
+            assert!(Cron::parse("0 0 0 * *").is_err());
+            assert!(Cron::parse("0 0 * 13 *").is_err());
+            assert!(Cron::parse("*/15 9-18 * * 1-5").is_ok());
+        }
+
+        #[test]
+        fn next_minute_hour_and_list() {
+            // the current minute is never returned, even if it matches
+            assert_eq!(
+                next("30 10 * * *", at(2024, 3, 5, 10, 30)),
+                Some(at(2024, 3, 6, 10, 30))
+            );
+            assert_eq!(
+                next("0 8,20 * * *", at(2024, 1, 1, 9, 0)),
+                Some(at(2024, 1, 1, 20, 0))
+            );
+            assert_eq!(
+                next("*/15 * * * *", at(2024, 1, 1, 9, 46)),
+                Some(at(2024, 1, 1, 10, 0))
+            );
+        }
+
+        #[test]
+        fn next_on_working_days() {
+            // 2024-03-08 is a friday
+            assert_eq!(
+                next("*/15 9-18 * * 1-5", at(2024, 3, 8, 18, 50)),
+                Some(at(2024, 3, 11, 9, 0))
+            );
+        }
+
+        #[test]
+        fn day_of_month_or_day_of_week() {
+            // the 13th or any friday, whichever comes first
+            assert_eq!(
+                next("0 12 13 * 5", at(2024, 3, 9, 0, 0)),
+                Some(at(2024, 3, 13, 12, 0))
+            );
+            assert_eq!(
+                next("0 12 13 * 5", at(2024, 3, 13, 12, 0)),
+                Some(at(2024, 3, 15, 12, 0))
+            );
+            // only the day of the month when the day of the week is *
+            assert_eq!(
+                next("0 12 13 * *", at(2024, 3, 13, 12, 0)),
+                Some(at(2024, 4, 13, 12, 0))
+            );
+            // 0 and 7 are both sunday
+            assert_eq!(
+                next("0 0 * * 7", at(2024, 3, 13, 0, 0)),
+                Some(at(2024, 3, 17, 0, 0))
+            );
+            assert_eq!(
+                next("0 0 * * 0", at(2024, 3, 13, 0, 0)),
+                Some(at(2024, 3, 17, 0, 0))
+            );
+        }
+
+        #[test]
+        fn next_far_away_or_never() {
+            assert_eq!(
+                next("0 0 29 2 *", at(2024, 3, 1, 0, 0)),
+                Some(at(2028, 2, 29, 0, 0))
+            );
+            assert_eq!(
+                next("0 0 1 1 *", at(2024, 12, 31, 23, 59)),
+                Some(at(2025, 1, 1, 0, 0))
+            );
+            assert_eq!(next("0 0 31 4 *", at(2024, 1, 1, 0, 0)), None);
+            assert_eq!(next("0 0 30 2 *", at(2024, 1, 1, 0, 0)), None);
+        }
+    }
+}
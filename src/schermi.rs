@@ -1,7 +1,27 @@
 pub mod schermi {
+    use crate::error::error::AppError;
+    use crate::RectangleCrop;
     use eframe::WindowInfo;
+    use image::RgbaImage;
     use screenshots::Screen;
 
+    // captures the area (or the whole screen) as raw pixels
+    pub fn capture_rgba(screen: &Screen, rect: Option<&RectangleCrop>) -> Option<RgbaImage> {
+        let image = match rect {
+            Some(rect) => screen
+                .capture_area(
+                    rect.x_left.floor() as i32,
+                    rect.y_left.floor() as i32,
+                    rect.width.floor() as u32,
+                    rect.height.floor() as u32,
+                )
+                .ok()?,
+            None => screen.capture().ok()?,
+        };
+        let png = image.to_png(None).ok()?;
+        Some(image::load_from_memory(&png).ok()?.to_rgba8())
+    }
+
     pub struct Schermi {
         screens: Vec<Screen>,
         pub screen_no: usize,
@@ -12,7 +32,7 @@ pub mod schermi {
     impl Schermi {
         pub fn new() -> Self {
             Schermi {
-                screens: Screen::all().unwrap(),
+                screens: Screen::all().unwrap_or_default(),
                 screen_no: 0,
                 show_screen_options: false,
                 default_screen_no: 0,
@@ -27,7 +47,7 @@ pub mod schermi {
                 match Screen::from_point(x, y) {
                     Ok(screen_info) => {
                         let id_screen = screen_info.display_info.id;
-                        let list_screen = Screen::all().unwrap();
+                        let list_screen = Screen::all().unwrap_or_default();
                         if let Some(position) = list_screen
                             .iter()
                             .position(|&screen| screen.display_info.id == id_screen)
@@ -45,12 +65,15 @@ pub mod schermi {
             self.screens.len() as usize
         }
 
-        pub fn get_screen(&self) -> Screen {
-            self.screens[self.screen_no]
+        pub fn get_screen(&self) -> Result<Screen, AppError> {
+            self.screens.get(self.screen_no).copied().ok_or(AppError::NoScreen)
         }
 
-        pub fn get_default_screen(&self) -> Screen {
-            self.screens[self.default_screen_no]
+        pub fn get_default_screen(&self) -> Result<Screen, AppError> {
+            self.screens
+                .get(self.default_screen_no)
+                .copied()
+                .ok_or(AppError::NoScreen)
         }
     }
 }
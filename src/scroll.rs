@@ -0,0 +1,261 @@
+pub mod scroll {
+    use crate::schermi::schermi::capture_rgba;
+    use crate::RectangleCrop;
+    use image::{GenericImage, GenericImageView, RgbaImage};
+    use screenshots::Screen;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashMap;
+    use std::hash::{Hash, Hasher};
+    use std::time::Duration;
+
+    #[cfg(feature = "scroll")]
+    const SCROLL_STEP: i32 = 3;
+    const SETTLE_TIME: Duration = Duration::from_millis(250);
+    const MAX_FRAMES: usize = 200;
+    const MIN_OVERLAP: usize = 20;
+    const MATCH_RATIO: f32 = 0.95;
+
+    pub struct Stitcher {
+        result: RgbaImage,
+        last_rows: Vec<u64>,
+        unchanged: u32,
+    }
+
+    impl Stitcher {
+        pub fn new(first: RgbaImage) -> Self {
+            Stitcher {
+                last_rows: row_hashes(&first),
+                result: first,
+                unchanged: 0,
+            }
+        }
+
+        // appends the new part of the frame, returns false when the capture should stop
+        pub fn push(&mut self, frame: RgbaImage) -> bool {
+            let rows = row_hashes(&frame);
+            let shift = match find_shift(&self.last_rows, &rows) {
+                Some(shift) => shift,
+                None => return false,
+            };
+            if shift == 0 {
+                self.unchanged += 1;
+                return self.unchanged < 2;
+            }
+            self.unchanged = 0;
+
+            let height = frame.height();
+            let new_rows = shift as u32;
+            let mut result = RgbaImage::new(self.result.width(), self.result.height() + new_rows);
+            result.copy_from(&self.result, 0, 0).unwrap();
+            result
+                .copy_from(
+                    &*frame.view(0, height - new_rows, frame.width(), new_rows),
+                    0,
+                    self.result.height(),
+                )
+                .unwrap();
+            self.result = result;
+            self.last_rows = rows;
+            true
+        }
+
+        pub fn into_image(self) -> RgbaImage {
+            self.result
+        }
+    }
+
+    fn row_hashes(image: &RgbaImage) -> Vec<u64> {
+        image
+            .rows()
+            .map(|row| {
+                let mut hasher = DefaultHasher::new();
+                for pixel in row {
+                    pixel.0.hash(&mut hasher);
+                }
+                hasher.finish()
+            })
+            .collect()
+    }
+
+    // number of rows the content moved up between two frames of the same size;
+    // 0 only when nothing changed, otherwise the matching shift whose overlap has the
+    // most rows that appear once in the frame, so blank or uniform bands, which match
+    // at almost any shift, do not decide it; on a tie the smallest shift wins
+    fn find_shift(previous: &[u64], next: &[u64]) -> Option<usize> {
+        let height = previous.len().min(next.len());
+        if height <= MIN_OVERLAP {
+            return None;
+        }
+        if previous[..height] == next[..height] {
+            return Some(0);
+        }
+        let mut counts = HashMap::new();
+        for row in &previous[..height] {
+            *counts.entry(row).or_insert(0) += 1;
+        }
+        let mut best: Option<(usize, usize)> = None;
+        for shift in 1..(height - MIN_OVERLAP) {
+            let overlap = height - shift;
+            let mut matching = 0;
+            let mut distinct = 0;
+            for i in 0..overlap {
+                if previous[shift + i] == next[i] {
+                    matching += 1;
+                    if counts[&previous[shift + i]] == 1 {
+                        distinct += 1;
+                    }
+                }
+            }
+            let better = best.is_none_or(|(_, score)| distinct > score);
+            if matching as f32 >= overlap as f32 * MATCH_RATIO && better {
+                best = Some((shift, distinct));
+            }
+        }
+        best.map(|(shift, _)| shift)
+    }
+
+    // moves the mouse over the selection and turns its wheel
+    #[cfg(feature = "scroll")]
+    struct Scroller(enigo::Enigo);
+
+    #[cfg(feature = "scroll")]
+    impl Scroller {
+        fn new(x: i32, y: i32) -> Option<Self> {
+            use enigo::{Coordinate, Enigo, Mouse, Settings};
+            let mut enigo = Enigo::new(&Settings::default()).ok()?;
+            enigo.move_mouse(x, y, Coordinate::Abs).ok()?;
+            Some(Scroller(enigo))
+        }
+
+        fn scroll(&mut self) -> bool {
+            use enigo::{Axis, Mouse};
+            self.0.scroll(SCROLL_STEP, Axis::Vertical).is_ok()
+        }
+    }
+
+    // without the scroll feature the wheel can't be driven: a normal capture is taken
+    #[cfg(not(feature = "scroll"))]
+    struct Scroller;
+
+    #[cfg(not(feature = "scroll"))]
+    impl Scroller {
+        fn new(_x: i32, _y: i32) -> Option<Self> {
+            None
+        }
+
+        fn scroll(&mut self) -> bool {
+            false
+        }
+    }
+
+    // scrolls the content under the selection and stitches the frames into one tall png
+    pub fn scroll_capture(screen: Screen, rect: RectangleCrop) -> Option<Vec<u8>> {
+        let center_x = screen.display_info.x + (rect.x_left + rect.width / 2.0) as i32;
+        let center_y = screen.display_info.y + (rect.y_left + rect.height / 2.0) as i32;
+        let mut scroller = Scroller::new(center_x, center_y)?;
+
+        let mut stitcher = Stitcher::new(capture_rgba(&screen, Some(&rect))?);
+        for _ in 0..MAX_FRAMES {
+            if !scroller.scroll() {
+                break;
+            }
+            std::thread::sleep(SETTLE_TIME);
+            match capture_rgba(&screen, Some(&rect)) {
+                Some(frame) => {
+                    if !stitcher.push(frame) {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+
+        let mut output = Vec::new();
+        stitcher
+            .into_image()
+            .write_to(
+                &mut std::io::Cursor::new(&mut output),
+                image::ImageOutputFormat::Png,
+            )
+            .ok()?;
+        Some(output)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use image::Rgba;
+
+        const WIDTH: u32 = 8;
+        const HEIGHT: u32 = 100;
+
+        // every row different, except the white bands
+        fn page(rows: u32, blank: &[(u32, u32)]) -> RgbaImage {
+            RgbaImage::from_fn(WIDTH, rows, |x, y| {
+                if blank.iter().any(|&(start, end)| (start..end).contains(&y)) {
+                    Rgba([255, 255, 255, 255])
+                } else {
+                    Rgba([(y % 256) as u8, (y / 256) as u8, x as u8, 255])
+                }
+            })
+        }
+
+        fn frame(page: &RgbaImage, offset: u32) -> RgbaImage {
+            page.view(0, offset, WIDTH, HEIGHT).to_image()
+        }
+
+        #[test]
+        fn known_scroll_is_found() {
+            let page = page(300, &[]);
+            let shift = find_shift(
+                &row_hashes(&frame(&page, 0)),
+                &row_hashes(&frame(&page, 13)),
+            );
+            assert_eq!(shift, Some(13));
+        }
+
+        #[test]
+        fn unchanged_frame_stops_the_capture() {
+            let page = page(300, &[]);
+            let first = frame(&page, 0);
+            assert_eq!(
+                find_shift(&row_hashes(&first), &row_hashes(&first)),
+                Some(0)
+            );
+            let mut stitcher = Stitcher::new(first.clone());
+            assert!(stitcher.push(first.clone()));
+            assert!(!stitcher.push(first.clone()));
+            assert_eq!(stitcher.into_image(), first);
+        }
+
+        #[test]
+        fn blank_bands_do_not_decide_the_shift() {
+            // the bottom of a frame and the top of the next are both blank,
+            // so the largest shift with a 20 rows overlap matches too
+            let page = page(300, &[(0, 30), (80, 130)]);
+            let shift = find_shift(&row_hashes(&frame(&page, 0)), &row_hashes(&frame(&page, 5)));
+            assert_eq!(shift, Some(5));
+        }
+
+        #[test]
+        fn frames_are_stitched_into_the_page() {
+            let page = page(300, &[(0, 30), (80, 130), (190, 200)]);
+            let mut stitcher = Stitcher::new(frame(&page, 0));
+            for offset in [5, 12, 60, 110, 150] {
+                assert!(stitcher.push(frame(&page, offset)));
+            }
+            let expected = page.view(0, 0, WIDTH, 150 + HEIGHT).to_image();
+            assert_eq!(stitcher.into_image(), expected);
+        }
+
+        #[test]
+        fn unrelated_frames_do_not_match() {
+            let first = page(300, &[]);
+            let other = RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| Rgba([x as u8, y as u8, 9, 255]));
+            assert_eq!(
+                find_shift(&row_hashes(&frame(&first, 0)), &row_hashes(&other)),
+                None
+            );
+        }
+    }
+}
@@ -0,0 +1,50 @@
+pub mod selection {
+    use std::fmt;
+
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum SelectionPreset {
+        Free,
+        Ratio(u32, u32),
+        Size(u32, u32),
+    }
+
+    impl SelectionPreset {
+        pub fn all() -> Vec<SelectionPreset> {
+            vec![
+                SelectionPreset::Free,
+                SelectionPreset::Ratio(16, 9),
+                SelectionPreset::Ratio(4, 3),
+                SelectionPreset::Ratio(1, 1),
+                SelectionPreset::Size(1280, 720),
+                SelectionPreset::Size(1920, 1080),
+            ]
+        }
+
+        // returns the (width, height) closest to the given one that respects the preset;
+        // with a ratio, the side the user edited is kept and the other one follows
+        pub fn constrain(self, width: f32, height: f32, by_height: bool) -> (f32, f32) {
+            match self {
+                SelectionPreset::Free => (width.round(), height.round()),
+                SelectionPreset::Ratio(w, h) if by_height => {
+                    let height = height.round().max(1.0);
+                    ((height * w as f32 / h as f32).round().max(1.0), height)
+                }
+                SelectionPreset::Ratio(w, h) => {
+                    let width = width.round().max(1.0);
+                    (width, (width * h as f32 / w as f32).round().max(1.0))
+                }
+                SelectionPreset::Size(w, h) => (w as f32, h as f32),
+            }
+        }
+    }
+
+    impl fmt::Display for SelectionPreset {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                SelectionPreset::Free => write!(f, "Free"),
+                SelectionPreset::Ratio(w, h) => write!(f, "{}:{}", w, h),
+                SelectionPreset::Size(w, h) => write!(f, "{}x{}", w, h),
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
 pub mod shortcut {
     use crate::action::Action;
+    use crate::regions::regions::Region;
     use eframe::egui;
     use egui::{Key, KeyboardShortcut, Modifiers};
+    use serde::{Deserialize, Serialize};
 
     pub struct NewShortcut {
         pub modifier: Modifiers,
@@ -35,6 +37,15 @@ pub mod shortcut {
         action: Action,
     }
 
+    // a shortcut bound to a saved region, kept in the config
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct RegionShortcut {
+        pub region: u32,
+        pub modifiers: Modifiers,
+        pub key: Key,
+        pub is_active: bool,
+    }
+
     pub struct ShortcutSet {
         set: Vec<ShortCut>,
         pub show: bool,
@@ -250,6 +261,58 @@ pub mod shortcut {
                     wants_image_viewer: false,
                     action: Action::StartTimer,
                 };
+                let s_repeat_last_capture = ShortCut {
+                    name: Action::RepeatLastCapture.to_string(),
+                    shortcut: KeyboardShortcut::new(Modifiers::COMMAND, Key::R),
+                    is_active: true,
+                    wants_image_viewer: false,
+                    action: Action::RepeatLastCapture,
+                };
+                let s_scroll_capture = ShortCut {
+                    name: Action::ScrollCapture.to_string(),
+                    shortcut: KeyboardShortcut::new(
+                        Modifiers::COMMAND | Modifiers::SHIFT,
+                        Key::Enter,
+                    ),
+                    is_active: true,
+                    wants_image_viewer: false,
+                    action: Action::ScrollCapture,
+                };
+                let s_record = ShortCut {
+                    name: Action::Record.to_string(),
+                    shortcut: KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::R),
+                    is_active: true,
+                    wants_image_viewer: false,
+                    action: Action::Record,
+                };
+                let s_watch = ShortCut {
+                    name: Action::Watch.to_string(),
+                    shortcut: KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::W),
+                    is_active: true,
+                    wants_image_viewer: false,
+                    action: Action::Watch,
+                };
+                let s_quick_save = ShortCut {
+                    name: Action::QuickSave.to_string(),
+                    shortcut: KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::S),
+                    is_active: true,
+                    wants_image_viewer: true,
+                    action: Action::QuickSave,
+                };
+                let s_open_image = ShortCut {
+                    name: Action::OpenImage.to_string(),
+                    shortcut: KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::O),
+                    is_active: true,
+                    wants_image_viewer: false,
+                    action: Action::OpenImage,
+                };
+                let s_paste = ShortCut {
+                    name: Action::Paste.to_string(),
+                    shortcut: KeyboardShortcut::new(Modifiers::COMMAND, Key::V),
+                    is_active: true,
+                    wants_image_viewer: false,
+                    action: Action::Paste,
+                };
                 output.push(s_save);
                 output.push(s_set_entire_screen);
                 output.push(s_set_selection);
@@ -263,6 +326,13 @@ pub mod shortcut {
                 output.push(s_another_screenshot);
                 output.push(s_copy);
                 output.push(s_undo);
+                output.push(s_repeat_last_capture);
+                output.push(s_scroll_capture);
+                output.push(s_record);
+                output.push(s_watch);
+                output.push(s_quick_save);
+                output.push(s_open_image);
+                output.push(s_paste);
                 
             }
 
@@ -301,6 +371,55 @@ pub mod shortcut {
                 None
             }
         }
+        pub fn region_shortcuts(&self) -> Vec<RegionShortcut> {
+            self.set
+                .iter()
+                .filter_map(|sc| match sc.action {
+                    Action::CaptureRegion(region) => Some(RegionShortcut {
+                        region,
+                        modifiers: sc.shortcut.modifiers,
+                        key: sc.shortcut.key,
+                        is_active: sc.is_active,
+                    }),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        // shortcuts of regions that no longer exist or keys already taken are dropped
+        pub fn load_region_shortcuts(&mut self, shortcuts: &[RegionShortcut], regions: &[Region]) {
+            for saved in shortcuts {
+                if !regions.iter().any(|r| r.id == saved.region) {
+                    continue;
+                }
+                let mut sc = ShortCut::shortcut_builder(
+                    saved.modifiers,
+                    saved.key,
+                    Action::CaptureRegion(saved.region),
+                );
+                sc.is_active = saved.is_active;
+                if !self.set.iter().any(|s| s.shortcut.eq(&sc.shortcut)) {
+                    self.set.push(sc);
+                }
+            }
+            self.name_regions(regions);
+        }
+
+        // the region shortcuts show the name of their region
+        pub fn name_regions(&mut self, regions: &[Region]) {
+            for sc in self.set.iter_mut() {
+                if let Action::CaptureRegion(id) = sc.action {
+                    if let Some(region) = regions.iter().find(|r| r.id == id) {
+                        sc.name = format!("{} ({})", sc.action.to_string(), region.name);
+                    }
+                }
+            }
+        }
+
+        pub fn delete_action(&mut self, action: Action) {
+            self.set.retain(|sc| sc.action != action);
+        }
+
         pub fn delete_shotucut(&mut self, shortcut: &mut ShortCut) {
             let mut delete_index = 0;
             for (i, sc) in self.set.iter().enumerate() {
@@ -319,6 +438,10 @@ pub mod shortcut {
                         }
                     }
                 } else {
+                    // the keys of a paste also reach the text field being edited
+                    if sc.action == Action::Paste && ctx.wants_keyboard_input() {
+                        continue;
+                    }
                     if sc.wants_image_viewer == is_image && sc.is_active {
                         if let Some(opt_action) = sc.listener_shortcut(ctx) {
                             return Some(opt_action);
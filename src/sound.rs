@@ -0,0 +1,73 @@
+pub mod sound {
+    #[cfg(feature = "sound")]
+    const SAMPLE_RATE: u32 = 44100;
+
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum Sound {
+        Tick,
+        Shutter,
+    }
+
+    // plays on its own thread; a missing audio device or a bad file is silently ignored
+    #[cfg(feature = "sound")]
+    pub fn play(sound: Sound, wav: &str) {
+        use rodio::buffer::SamplesBuffer;
+        use rodio::{Decoder, OutputStream, Sink};
+        use std::fs::File;
+        use std::io::BufReader;
+
+        let wav = wav.trim().to_string();
+        std::thread::spawn(move || {
+            let (_stream, handle) = match OutputStream::try_default() {
+                Ok(output) => output,
+                Err(_) => return,
+            };
+            let sink = match Sink::try_new(&handle) {
+                Ok(sink) => sink,
+                Err(_) => return,
+            };
+            let decoded = File::open(&wav)
+                .ok()
+                .and_then(|file| Decoder::new(BufReader::new(file)).ok());
+            match decoded {
+                Some(decoder) => sink.append(decoder),
+                None => sink.append(SamplesBuffer::new(1, SAMPLE_RATE, generate(sound))),
+            }
+            sink.sleep_until_end();
+        });
+    }
+
+    // without the sound feature there is no audio backend, only the terminal bell
+    #[cfg(not(feature = "sound"))]
+    pub fn play(_sound: Sound, _wav: &str) {
+        use std::io::Write;
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(b"\x07").and_then(|()| stdout.flush());
+    }
+
+    // a short beep for the tick, a burst of decaying noise for the shutter
+    #[cfg(feature = "sound")]
+    fn generate(sound: Sound) -> Vec<f32> {
+        let (length, decay) = match sound {
+            Sound::Tick => (0.04, 60.0),
+            Sound::Shutter => (0.15, 30.0),
+        };
+        let samples = (SAMPLE_RATE as f32 * length) as usize;
+        let mut seed: u32 = 0x2545_f491;
+        (0..samples)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                let wave = match sound {
+                    Sound::Tick => (t * 1000.0 * std::f32::consts::TAU).sin(),
+                    Sound::Shutter => {
+                        seed ^= seed << 13;
+                        seed ^= seed >> 17;
+                        seed ^= seed << 5;
+                        seed as f32 / u32::MAX as f32 * 2.0 - 1.0
+                    }
+                };
+                wave * (-t * decay).exp() * 0.4
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,212 @@
+pub mod template {
+    use chrono::format::{Item, StrftimeItems};
+    use chrono::{DateTime, Local};
+
+    pub const DEFAULT_TEMPLATE: &str = "screenshot_{date:%Y%m%d}_{time}";
+    // wider paddings make no sense for a u32 and would make format! panic
+    const MAX_COUNTER_WIDTH: usize = 10;
+
+    // what the tokens of the template are replaced with
+    pub struct NameContext {
+        pub now: DateTime<Local>,
+        pub counter: u32,
+        pub screen: usize,
+        pub mode: String,
+        pub width: u32,
+        pub height: u32,
+        pub window_title: String,
+    }
+
+    // {date}, {date:%Y-%m-%d}, {time}, {time:%H%M}, {counter}, {counter:04}, {screen},
+    // {mode}, {width}, {height}, {window_title}; {{ and }} are literal braces
+    pub fn render(template: &str, context: &NameContext) -> Result<String, String> {
+        let mut name = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    name.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    name.push('}');
+                }
+                '{' => {
+                    let mut token = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => token.push(c),
+                            None => return Err(format!("Missing }} after {{{}", token)),
+                        }
+                    }
+                    name.push_str(&expand(&token, context)?);
+                }
+                '}' => return Err("Unexpected }".to_string()),
+                c => name.push(c),
+            }
+        }
+        let name = sanitize(&name);
+        if name.trim().is_empty() {
+            return Err("The name is empty".to_string());
+        }
+        Ok(name)
+    }
+
+    fn expand(token: &str, context: &NameContext) -> Result<String, String> {
+        let (key, argument) = match token.split_once(':') {
+            Some((key, argument)) => (key.trim(), Some(argument)),
+            None => (token.trim(), None),
+        };
+        match key {
+            "date" => format_time(&context.now, argument.unwrap_or("%Y-%m-%d")),
+            "time" => format_time(&context.now, argument.unwrap_or("%H_%M_%S")),
+            "counter" => {
+                let width = match argument {
+                    Some(width) => width
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| format!("Invalid counter width: {}", width))?,
+                    None => 0,
+                };
+                if width > MAX_COUNTER_WIDTH {
+                    return Err(format!(
+                        "The counter width can be at most {}",
+                        MAX_COUNTER_WIDTH
+                    ));
+                }
+                Ok(format!("{:0width$}", context.counter, width = width))
+            }
+            "screen" => Ok((context.screen + 1).to_string()),
+            "mode" => Ok(context.mode.clone()),
+            "width" => Ok(context.width.to_string()),
+            "height" => Ok(context.height.to_string()),
+            "window_title" => Ok(context.window_title.clone()),
+            _ => Err(format!("Unknown token: {{{}}}", key)),
+        }
+    }
+
+    // an invalid format would make chrono panic while writing it
+    fn format_time(time: &DateTime<Local>, format: &str) -> Result<String, String> {
+        if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+            return Err(format!("Invalid date format: {}", format));
+        }
+        Ok(time.format(format).to_string())
+    }
+
+    // characters that are not allowed in file names on some systems
+    fn sanitize(name: &str) -> String {
+        name.chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect()
+    }
+
+    // title of the window that has the focus, if the system tells it
+    #[cfg(target_os = "linux")]
+    pub fn active_window_title() -> Option<String> {
+        use x11rb::connection::Connection;
+        use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+
+        let (conn, screen_num) = x11rb::connect(None).ok()?;
+        let root = conn.setup().roots.get(screen_num)?.root;
+        let atom = |name: &[u8]| -> Option<u32> {
+            Some(conn.intern_atom(false, name).ok()?.reply().ok()?.atom)
+        };
+        let active = conn
+            .get_property(false, root, atom(b"_NET_ACTIVE_WINDOW")?, AtomEnum::WINDOW, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?
+            .value32()?
+            .next()?;
+        let title = conn
+            .get_property(false, active, atom(b"_NET_WM_NAME")?, atom(b"UTF8_STRING")?, 0, 256)
+            .ok()?
+            .reply()
+            .ok()?;
+        let title = if title.value.is_empty() {
+            conn.get_property(false, active, AtomEnum::WM_NAME, AtomEnum::STRING, 0, 256)
+                .ok()?
+                .reply()
+                .ok()?
+                .value
+        } else {
+            title.value
+        };
+        Some(String::from_utf8_lossy(&title).to_string())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn active_window_title() -> Option<String> {
+        None
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chrono::TimeZone;
+
+        fn context() -> NameContext {
+            NameContext {
+                now: Local.with_ymd_and_hms(2024, 3, 5, 14, 7, 9).unwrap(),
+                counter: 42,
+                screen: 0,
+                mode: "Selection".to_string(),
+                width: 800,
+                height: 600,
+                window_title: "a/b: c".to_string(),
+            }
+        }
+
+        #[test]
+        fn tokens_are_replaced() {
+            let name = render(
+                "{date}_{time}_{counter}_{screen}_{mode}_{width}x{height}",
+                &context(),
+            );
+            assert_eq!(name.unwrap(), "2024-03-05_14_07_09_42_1_Selection_800x600");
+            assert_eq!(
+                render("{date:%Y%m%d}-{time:%H%M}", &context()).unwrap(),
+                "20240305-1407"
+            );
+            assert_eq!(
+                render(DEFAULT_TEMPLATE, &context()).unwrap(),
+                "screenshot_20240305_14_07_09"
+            );
+        }
+
+        #[test]
+        fn counter_is_padded() {
+            assert_eq!(render("{counter:04}", &context()).unwrap(), "0042");
+            assert_eq!(render("{counter:1}", &context()).unwrap(), "42");
+            assert_eq!(render("{counter:10}", &context()).unwrap(), "0000000042");
+        }
+
+        #[test]
+        fn braces_and_unsafe_characters() {
+            assert_eq!(render("{{x}}", &context()).unwrap(), "{x}");
+            assert_eq!(render("{window_title}", &context()).unwrap(), "a_b_ c");
+        }
+
+        #[test]
+        fn invalid_placeholders_are_errors() {
+            for template in [
+                "{counter:11}",
+                "{counter:70000}",
+                "{counter:x}",
+                "{nope}",
+                "{date",
+                "date}",
+                "{date:%Q}",
+                "",
+            ] {
+                assert!(render(template, &context()).is_err(), "{}", template);
+            }
+        }
+    }
+}
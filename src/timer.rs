@@ -1,56 +1,168 @@
 pub mod timer {
-    use std::time::Instant;
+    use eframe::egui;
+    use serde::{Deserialize, Serialize};
+    use std::fmt;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
 
     #[derive(Clone)]
     pub struct Timer {
-        pub seconds: u32,
-        text: String,
+        pub text: String,
         timer_form_open: bool,
-        is_timer_running: bool,
-      pub  last_decrement_time:Option<Instant>,
+        deadline: Option<Instant>,
+        fired: Arc<AtomicU32>,
+        cancel: Option<Sender<()>>, // dropping it wakes up and stops the thread
     }
 
-    impl Timer {
+    // repeats a capture every `every`, for `shots` times (0 -> until stopped)
+    pub struct Interval {
+        pub every_text: String,
+        pub shots: u32,
+        form_open: bool,
+        start: Option<Instant>,
+        every: Duration,
+        taken: u32,
+        fired: Arc<AtomicU32>,
+        cancel: Option<Sender<()>>,
+    }
+
+    // where the countdown is shown on the screen
+    #[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub enum CountdownPosition {
+        TopLeft,
+        TopRight,
+        Center,
+        BottomLeft,
+        BottomRight,
+    }
+
+    impl CountdownPosition {
+        pub fn all() -> Vec<CountdownPosition> {
+            vec![
+                CountdownPosition::TopLeft,
+                CountdownPosition::TopRight,
+                CountdownPosition::Center,
+                CountdownPosition::BottomLeft,
+                CountdownPosition::BottomRight,
+            ]
+        }
+
+        pub fn anchor(self) -> (egui::Align2, egui::Vec2) {
+            match self {
+                CountdownPosition::TopLeft => (egui::Align2::LEFT_TOP, egui::vec2(10.0, 10.0)),
+                CountdownPosition::TopRight => (egui::Align2::RIGHT_TOP, egui::vec2(0.0, 10.0)),
+                CountdownPosition::Center => (egui::Align2::CENTER_CENTER, egui::Vec2::ZERO),
+                CountdownPosition::BottomLeft => {
+                    (egui::Align2::LEFT_BOTTOM, egui::vec2(10.0, -10.0))
+                }
+                CountdownPosition::BottomRight => {
+                    (egui::Align2::RIGHT_BOTTOM, egui::vec2(0.0, -10.0))
+                }
+            }
+        }
+    }
 
+    impl fmt::Display for CountdownPosition {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                CountdownPosition::TopLeft => write!(f, "Top left"),
+                CountdownPosition::TopRight => write!(f, "Top right"),
+                CountdownPosition::Center => write!(f, "Center"),
+                CountdownPosition::BottomLeft => write!(f, "Bottom left"),
+                CountdownPosition::BottomRight => write!(f, "Bottom right"),
+            }
+        }
+    }
+
+    // wakes the ui at every deadline of the schedule, until the sender is dropped
+    fn spawn_schedule(
+        ctx: &egui::Context,
+        first: Instant,
+        every: Duration,
+        count: u32,
+    ) -> (Sender<()>, Arc<AtomicU32>) {
+        let (sender, receiver) = mpsc::channel::<()>();
+        let fired = Arc::new(AtomicU32::new(0));
+        let fired_thread = fired.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let mut deadline = first;
+            let mut done = 0;
+            while count == 0 || done < count {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                match receiver.recv_timeout(remaining) {
+                    Err(RecvTimeoutError::Timeout) => {
+                        fired_thread.fetch_add(1, Ordering::SeqCst);
+                        ctx.request_repaint();
+                        done += 1;
+                        // deadlines don't drift with the time spent capturing
+                        deadline += every;
+                    }
+                    _ => break,
+                }
+            }
+        });
+        (sender, fired)
+    }
+
+    impl Timer {
         pub fn new() -> Self {
-            Timer {    
-                seconds: 0,
+            Timer {
                 text: "".to_string(),
                 timer_form_open: false,
-                is_timer_running: false,
-                last_decrement_time: None,
+                deadline: None,
+                fired: Arc::new(AtomicU32::new(0)),
+                cancel: None,
             }
         }
 
-        pub fn handle_negative_timer(&mut self) {
-            self.seconds = 0;
-            self.text = "".to_string();
-            self.is_timer_running = false;
+        pub fn get_delay(&self) -> Option<Duration> {
+            parse_duration(&self.text).filter(|d| !d.is_zero())
         }
 
-        pub fn handle_positive_timer(&mut self) {
-            self.seconds -= 1;
-        }
-
-        pub fn start_timer(&mut self) {
+        // the countdown runs on its own thread, which repaints the ui when the deadline is reached
+        pub fn start_timer(&mut self, ctx: &egui::Context) -> bool {
+            let delay = match self.get_delay() {
+                Some(delay) => delay,
+                None => return false,
+            };
+            let deadline = Instant::now() + delay;
+            let (sender, fired) = spawn_schedule(ctx, deadline, delay, 1);
             self.timer_form_open = false;
-            self.is_timer_running = true;
-            self.last_decrement_time= Some(Instant::now());
+            self.deadline = Some(deadline);
+            self.fired = fired;
+            self.cancel = Some(sender);
+            true
         }
 
         pub fn cancel_timer(&mut self) {
             self.timer_form_open = false;
-            self.seconds = 0;
             self.text = "".to_string();
-            self.is_timer_running = false;
+            self.deadline = None;
+            self.cancel = None;
+        }
+
+        // true once, when the deadline has been reached
+        pub fn take_fired(&mut self) -> bool {
+            if self.deadline.is_some() && self.fired.swap(0, Ordering::SeqCst) > 0 {
+                self.cancel_timer();
+                true
+            } else {
+                false
+            }
         }
 
         pub fn is_timer_running(&self) -> bool {
-            self.is_timer_running
+            self.deadline.is_some()
         }
 
-        pub fn get_seconds(&self) -> u32 {
-            self.seconds
+        pub fn remaining(&self) -> Duration {
+            match self.deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => Duration::ZERO,
+            }
         }
 
         pub fn open_timer_form(&mut self) {
@@ -60,13 +172,214 @@ pub mod timer {
         pub fn is_timer_form_open(&self) -> bool {
             self.timer_form_open
         }
+    }
 
-        pub fn close_timer_form(&mut self) {
-            self.timer_form_open = false;
+    impl Interval {
+        pub fn new() -> Self {
+            Interval {
+                every_text: "".to_string(),
+                shots: 0,
+                form_open: false,
+                start: None,
+                every: Duration::ZERO,
+                taken: 0,
+                fired: Arc::new(AtomicU32::new(0)),
+                cancel: None,
+            }
         }
-        pub fn last_decrement(&self)->Option<Instant>{
-            self.last_decrement_time
+
+        pub fn get_every(&self) -> Option<Duration> {
+            parse_duration(&self.every_text).filter(|d| !d.is_zero())
+        }
+
+        // the first shot is taken right away
+        pub fn start(&mut self, ctx: &egui::Context) -> bool {
+            let every = match self.get_every() {
+                Some(every) => every,
+                None => return false,
+            };
+            let start = Instant::now();
+            let (sender, fired) = spawn_schedule(ctx, start, every, self.shots);
+            self.form_open = false;
+            self.start = Some(start);
+            self.every = every;
+            self.taken = 0;
+            self.fired = fired;
+            self.cancel = Some(sender);
+            true
+        }
+
+        pub fn stop(&mut self) {
+            self.form_open = false;
+            self.start = None;
+            self.cancel = None;
+        }
+
+        // true when a shot has to be taken; shots missed while busy are merged into one,
+        // but they still count, or the interval would never reach its last shot
+        pub fn take_fired(&mut self) -> bool {
+            if self.start.is_none() {
+                return false;
+            }
+            let fired = self.fired.swap(0, Ordering::SeqCst);
+            if fired == 0 {
+                return false;
+            }
+            self.taken = self.taken.saturating_add(fired);
+            if self.shots != 0 {
+                self.taken = self.taken.min(self.shots);
+            }
+            true
+        }
+
+        // called after the shot has been saved
+        pub fn check_finished(&mut self) {
+            if self.shots != 0 && self.taken >= self.shots {
+                self.stop();
+            }
+        }
+
+        pub fn is_running(&self) -> bool {
+            self.start.is_some()
+        }
+
+        pub fn taken(&self) -> u32 {
+            self.taken
+        }
+
+        pub fn next_in(&self) -> Duration {
+            match self.start {
+                Some(start) => {
+                    (start + self.every * self.taken).saturating_duration_since(Instant::now())
+                }
+                None => Duration::ZERO,
+            }
+        }
+
+        pub fn toggle_form(&mut self) {
+            self.form_open = !self.form_open;
+        }
+
+        pub fn is_form_open(&self) -> bool {
+            self.form_open
+        }
+    }
+
+    // accepts plain seconds ("90", "2.5") or units ("1h", "1m30s", "1.5s", "500ms")
+    pub fn parse_duration(text: &str) -> Option<Duration> {
+        let text = text.trim().to_lowercase();
+        if text.is_empty() {
+            return None;
+        }
+        if let Ok(seconds) = text.parse::<f64>() {
+            return Duration::try_from_secs_f64(seconds).ok();
+        }
+        let mut total = 0.0;
+        let mut number = String::new();
+        let mut chars = text.chars().filter(|c| !c.is_whitespace()).peekable();
+        while let Some(c) = chars.next() {
+            if c.is_ascii_digit() || c == '.' {
+                number.push(c);
+                continue;
+            }
+            let value: f64 = number.parse().ok()?;
+            number.clear();
+            let unit = match c {
+                'h' => 3600.0,
+                'm' if chars.peek() == Some(&'s') => {
+                    chars.next();
+                    0.001
+                }
+                'm' => 60.0,
+                's' => 1.0,
+                _ => return None,
+            };
+            total += value * unit;
+        }
+        if !number.is_empty() {
+            return None;
+        }
+        Duration::try_from_secs_f64(total).ok()
+    }
+
+    pub fn format_duration(duration: Duration) -> String {
+        let seconds = duration.as_secs();
+        if seconds >= 3600 {
+            format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+        } else if seconds >= 60 {
+            format!("{}:{:02}", seconds / 60, seconds % 60)
+        } else {
+            format!("{:.1}", duration.as_secs_f32())
         }
     }
 
-}
\ No newline at end of file
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn durations_are_parsed() {
+            assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+            assert_eq!(parse_duration(" 2.5 "), Some(Duration::from_millis(2500)));
+            assert_eq!(parse_duration("1h"), Some(Duration::from_secs(3600)));
+            assert_eq!(parse_duration("1m30s"), Some(Duration::from_secs(90)));
+            assert_eq!(parse_duration("1M 30S"), Some(Duration::from_secs(90)));
+            assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+            assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+            assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+            for text in ["", "  ", "s", "10x", "1m30", "-5", "1..5s"] {
+                assert_eq!(parse_duration(text), None, "{}", text);
+            }
+        }
+
+        fn running(shots: u32) -> Interval {
+            let mut interval = Interval::new();
+            interval.shots = shots;
+            interval.every = Duration::from_secs(1);
+            interval.start = Some(Instant::now());
+            interval
+        }
+
+        #[test]
+        fn every_fire_is_a_shot() {
+            let mut interval = running(3);
+            assert!(!interval.take_fired());
+            for taken in 1..=3 {
+                interval.fired.fetch_add(1, Ordering::SeqCst);
+                assert!(interval.take_fired());
+                assert_eq!(interval.taken(), taken);
+                interval.check_finished();
+            }
+            assert!(!interval.is_running());
+        }
+
+        #[test]
+        fn shots_missed_while_busy_still_count() {
+            let mut interval = running(3);
+            interval.fired.fetch_add(1, Ordering::SeqCst);
+            assert!(interval.take_fired());
+            interval.check_finished();
+            // the capture took longer than the interval: the last two fired together
+            interval.fired.fetch_add(2, Ordering::SeqCst);
+            assert!(interval.take_fired());
+            assert_eq!(interval.taken(), 3);
+            interval.check_finished();
+            assert!(!interval.is_running());
+        }
+
+        #[test]
+        fn taken_does_not_pass_the_shots() {
+            let mut interval = running(2);
+            interval.fired.fetch_add(5, Ordering::SeqCst);
+            assert!(interval.take_fired());
+            assert_eq!(interval.taken(), 2);
+
+            let mut endless = running(0);
+            endless.fired.fetch_add(5, Ordering::SeqCst);
+            assert!(endless.take_fired());
+            endless.check_finished();
+            assert_eq!(endless.taken(), 5);
+            assert!(endless.is_running());
+        }
+    }
+}
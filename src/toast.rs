@@ -0,0 +1,101 @@
+pub mod toast {
+    use eframe::egui;
+    use egui::{Color32, RichText};
+    use std::time::{Duration, Instant};
+
+    const INFO_TIME: Duration = Duration::from_secs(4);
+    const ERROR_TIME: Duration = Duration::from_secs(10);
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum ToastKind {
+        Info,
+        Error,
+    }
+
+    struct Toast {
+        text: String,
+        kind: ToastKind,
+        shown: Instant,
+    }
+
+    // short notifications stacked in the bottom right corner, closed by a click or after a while
+    pub struct Toasts {
+        list: Vec<Toast>,
+    }
+
+    impl Toasts {
+        pub fn new() -> Self {
+            Toasts { list: Vec::new() }
+        }
+
+        fn push(&mut self, kind: ToastKind, text: impl Into<String>) {
+            self.list.push(Toast {
+                text: text.into(),
+                kind,
+                shown: Instant::now(),
+            });
+        }
+
+        pub fn info(&mut self, text: impl Into<String>) {
+            self.push(ToastKind::Info, text);
+        }
+
+        pub fn error(&mut self, text: impl Into<String>) {
+            self.push(ToastKind::Error, text);
+        }
+
+        pub fn show(&mut self, ctx: &egui::Context) {
+            self.list.retain(|t| t.shown.elapsed() < t.duration());
+            if self.list.is_empty() {
+                return;
+            }
+            let mut closed = None;
+            egui::Area::new("toasts")
+                .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+                .order(egui::Order::Foreground)
+                .show(ctx, |ui| {
+                    for (i, toast) in self.list.iter().enumerate() {
+                        let color = match toast.kind {
+                            ToastKind::Info => Color32::BLACK,
+                            ToastKind::Error => Color32::DARK_RED,
+                        };
+                        let response = egui::Frame {
+                            fill: Color32::GRAY,
+                            stroke: egui::Stroke::new(0.5, Color32::BLACK),
+                            inner_margin: egui::style::Margin::same(10.0),
+                            rounding: egui::Rounding::same(10.0),
+                            ..Default::default()
+                        }
+                        .show(ui, |ui| {
+                            ui.set_max_width(350.0);
+                            ui.label(RichText::new(&toast.text).color(color));
+                        })
+                        .response;
+                        if response.interact(egui::Sense::click()).clicked() {
+                            closed = Some(i);
+                        }
+                        ui.add_space(5.0);
+                    }
+                });
+            if let Some(i) = closed {
+                self.list.remove(i);
+            }
+            let next = self
+                .list
+                .iter()
+                .map(|t| t.duration().saturating_sub(t.shown.elapsed()))
+                .min()
+                .unwrap_or_default();
+            ctx.request_repaint_after(next);
+        }
+    }
+
+    impl Toast {
+        fn duration(&self) -> Duration {
+            match self.kind {
+                ToastKind::Info => INFO_TIME,
+                ToastKind::Error => ERROR_TIME,
+            }
+        }
+    }
+}
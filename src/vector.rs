@@ -0,0 +1,1173 @@
+pub mod vector {
+    use crate::error::error::AppError;
+    use crate::project::project::{Annotations, Point, Project, Rect, Style};
+    use ab_glyph::{Font, FontRef, ScaleFont};
+    use base64::Engine;
+    use eframe::egui;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use image::imageops::{self, FilterType};
+    use std::fmt::Write as _;
+    use std::io::Write;
+
+    // the same shapes the painter draws on the screen, in pixels of the capture
+    enum Element {
+        Polyline(Vec<Point>, Style, bool), // true when closed
+        Circle(Point, f32, Style),
+        Text(Point, String, Style),
+        Inset(Rect, Rect, bool), // the pixels of the source drawn in the inset, a circle if true
+    }
+
+    // in the order the painter draws them: magnifiers, arrows, texts, pen, lines, rectangles,
+    // circles
+    fn elements(annotations: &Annotations) -> Vec<Element> {
+        let mut elements = Vec::new();
+        for magnifier in &annotations.magnifier {
+            let style = magnifier.style;
+            elements.push(outline(magnifier.source, magnifier.circle, style));
+            if let Some((a, b)) = magnifier_link(magnifier.source, magnifier.inset, magnifier.circle)
+            {
+                elements.push(Element::Polyline(vec![a, b], style, false));
+            }
+            elements.push(Element::Inset(
+                magnifier.source,
+                magnifier.inset,
+                magnifier.circle,
+            ));
+            elements.push(outline(magnifier.inset, magnifier.circle, style));
+        }
+        let ends = |points: &Vec<Point>| Some((*points.first()?, *points.last()?));
+        for shape in &annotations.arrow {
+            if let Some((origin, tip)) = ends(&shape.points) {
+                // like egui's arrow: the head is a quarter of the length, at 36 degrees
+                let (dx, dy) = (tip.x - origin.x, tip.y - origin.y);
+                let length = (dx * dx + dy * dy).sqrt();
+                elements.push(Element::Polyline(vec![origin, tip], shape.style, false));
+                if length > 0.0 {
+                    let (dx, dy) = (dx / length, dy / length);
+                    let head = length / 4.0;
+                    let angle = std::f32::consts::TAU / 10.0;
+                    for a in [angle, -angle] {
+                        let (sin, cos) = a.sin_cos();
+                        let end = Point {
+                            x: tip.x - head * (cos * dx - sin * dy),
+                            y: tip.y - head * (sin * dx + cos * dy),
+                        };
+                        elements.push(Element::Polyline(vec![tip, end], shape.style, false));
+                    }
+                }
+            }
+        }
+        for text in &annotations.text {
+            elements.push(Element::Text(text.position, text.text.clone(), text.style));
+        }
+        for shape in annotations.pen.iter().filter(|s| s.points.len() >= 2) {
+            elements.push(Element::Polyline(shape.points.clone(), shape.style, false));
+        }
+        for shape in &annotations.line {
+            if let (true, Some((a, b))) = (shape.points.len() >= 2, ends(&shape.points)) {
+                elements.push(Element::Polyline(vec![a, b], shape.style, false));
+            }
+        }
+        for shape in &annotations.rect {
+            if let (true, Some((a, b))) = (shape.points.len() >= 2, ends(&shape.points)) {
+                let corners = vec![
+                    Point { x: a.x, y: a.y },
+                    Point { x: b.x, y: a.y },
+                    Point { x: b.x, y: b.y },
+                    Point { x: a.x, y: b.y },
+                ];
+                elements.push(Element::Polyline(corners, shape.style, true));
+            }
+        }
+        for shape in &annotations.circle {
+            if let (true, Some((center, edge))) = (shape.points.len() >= 2, ends(&shape.points)) {
+                let radius = ((edge.x - center.x).powi(2) + (edge.y - center.y).powi(2)).sqrt();
+                elements.push(Element::Circle(center, radius, shape.style));
+            }
+        }
+        elements
+    }
+
+    fn outline(rect: Rect, circle: bool, style: Style) -> Element {
+        if circle {
+            Element::Circle(center(rect), rect.width / 2.0, style)
+        } else {
+            let corners = vec![
+                Point { x: rect.x, y: rect.y },
+                Point {
+                    x: rect.x + rect.width,
+                    y: rect.y,
+                },
+                Point {
+                    x: rect.x + rect.width,
+                    y: rect.y + rect.height,
+                },
+                Point {
+                    x: rect.x,
+                    y: rect.y + rect.height,
+                },
+            ];
+            Element::Polyline(corners, style, true)
+        }
+    }
+
+    fn center(rect: Rect) -> Point {
+        Point {
+            x: rect.x + rect.width / 2.0,
+            y: rect.y + rect.height / 2.0,
+        }
+    }
+
+    // the line from the edge of the source to the edge of the inset, none if they overlap
+    pub fn magnifier_link(source: Rect, inset: Rect, circle: bool) -> Option<(Point, Point)> {
+        let (from, to) = (center(source), center(inset));
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let length = dx.hypot(dy);
+        if length == 0.0 {
+            return None;
+        }
+        let (dx, dy) = (dx / length, dy / length);
+        // how far the edge is from the center, along the line
+        let edge = |rect: Rect| {
+            if circle {
+                rect.width / 2.0
+            } else {
+                let x = if dx == 0.0 { f32::MAX } else { rect.width / 2.0 / dx.abs() };
+                let y = if dy == 0.0 { f32::MAX } else { rect.height / 2.0 / dy.abs() };
+                x.min(y)
+            }
+        };
+        let (start, end) = (edge(source), length - edge(inset));
+        if start >= end {
+            return None;
+        }
+        Some((
+            Point {
+                x: from.x + dx * start,
+                y: from.y + dy * start,
+            },
+            Point {
+                x: from.x + dx * end,
+                y: from.y + dy * end,
+            },
+        ))
+    }
+
+    // maps the source on the inset: x' = scale.0 * x + offset.0, the same for y
+    fn inset_transform(source: Rect, inset: Rect) -> ((f32, f32), (f32, f32)) {
+        let scale = (
+            inset.width / source.width.max(f32::EPSILON),
+            inset.height / source.height.max(f32::EPSILON),
+        );
+        let offset = (inset.x - source.x * scale.0, inset.y - source.y * scale.1);
+        (scale, offset)
+    }
+
+    // the painter uses the stroke width for the size of the text
+    fn font_size(style: &Style) -> f32 {
+        style.width * 20.0 + 0.1
+    }
+
+    // egui colors are premultiplied
+    fn color(style: &Style) -> ([u8; 3], f32) {
+        let [r, g, b, a] = style.color;
+        if a == 0 {
+            return ([0, 0, 0], 0.0);
+        }
+        let unmultiply = |c: u8| (c as u32 * 255 / a as u32).min(255) as u8;
+        (
+            [unmultiply(r), unmultiply(g), unmultiply(b)],
+            a as f32 / 255.0,
+        )
+    }
+
+    fn number(n: f32) -> String {
+        let text = format!("{:.2}", n);
+        let text = text.trim_end_matches('0').trim_end_matches('.');
+        if text == "-0" {
+            "0".to_string()
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn image_size(buffer: &[u8]) -> Result<(u32, u32), AppError> {
+        image::io::Reader::new(std::io::Cursor::new(buffer))
+            .with_guessed_format()
+            .map_err(|e| AppError::Image(e.to_string()))?
+            .into_dimensions()
+            .map_err(|e| AppError::Image(e.to_string()))
+    }
+
+    pub fn svg(project: &Project) -> Result<String, AppError> {
+        let (width, height) = image_size(&project.image)?;
+        let mut svg = String::new();
+        let _ = writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+            width, height
+        );
+        let _ = writeln!(
+            svg,
+            r#"<image id="capture" x="0" y="0" width="{}" height="{}" xlink:href="data:image/png;base64,{}"/>"#,
+            width,
+            height,
+            base64::engine::general_purpose::STANDARD.encode(&project.image)
+        );
+        for (i, element) in elements(&project.document.annotations)
+            .into_iter()
+            .enumerate()
+        {
+            match element {
+                Element::Inset(source, inset, circle) => {
+                    // the capture again, scaled and clipped, so it stays at full resolution
+                    let ((sx, sy), (tx, ty)) = inset_transform(source, inset);
+                    let clip = if circle {
+                        let c = center(inset);
+                        format!(
+                            r#"<circle cx="{}" cy="{}" r="{}"/>"#,
+                            number(c.x),
+                            number(c.y),
+                            number(inset.width / 2.0)
+                        )
+                    } else {
+                        format!(
+                            r#"<rect x="{}" y="{}" width="{}" height="{}"/>"#,
+                            number(inset.x),
+                            number(inset.y),
+                            number(inset.width),
+                            number(inset.height)
+                        )
+                    };
+                    let _ = writeln!(
+                        svg,
+                        r##"<clipPath id="inset{0}">{1}</clipPath><g clip-path="url(#inset{0})"><use xlink:href="#capture" transform="matrix({2} 0 0 {3} {4} {5})"/></g>"##,
+                        i,
+                        clip,
+                        number(sx),
+                        number(sy),
+                        number(tx),
+                        number(ty)
+                    );
+                }
+                Element::Polyline(points, style, closed) => {
+                    let points: Vec<String> = points
+                        .iter()
+                        .map(|p| format!("{},{}", number(p.x), number(p.y)))
+                        .collect();
+                    let _ = writeln!(
+                        svg,
+                        r#"<{} points="{}" fill="none" {} stroke-linecap="round" stroke-linejoin="round"/>"#,
+                        if closed { "polygon" } else { "polyline" },
+                        points.join(" "),
+                        svg_stroke(&style)
+                    );
+                }
+                Element::Circle(center, radius, style) => {
+                    let _ = writeln!(
+                        svg,
+                        r#"<circle cx="{}" cy="{}" r="{}" fill="none" {}/>"#,
+                        number(center.x),
+                        number(center.y),
+                        number(radius),
+                        svg_stroke(&style)
+                    );
+                }
+                Element::Text(position, text, style) => {
+                    let size = font_size(&style);
+                    let ([r, g, b], alpha) = color(&style);
+                    let _ = write!(
+                        svg,
+                        r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{}" fill="rgb({},{},{})" fill-opacity="{}" xml:space="preserve">"#,
+                        number(position.x),
+                        number(position.y + size * 0.8),
+                        number(size),
+                        r,
+                        g,
+                        b,
+                        number(alpha)
+                    );
+                    for (i, line) in text.lines().enumerate() {
+                        let _ = write!(
+                            svg,
+                            r#"<tspan x="{}" dy="{}">{}</tspan>"#,
+                            number(position.x),
+                            number(if i == 0 { 0.0 } else { size * 1.2 }),
+                            escape_xml(line)
+                        );
+                    }
+                    let _ = writeln!(svg, "</text>");
+                }
+            }
+        }
+        svg.push_str("</svg>\n");
+        Ok(svg)
+    }
+
+    fn svg_stroke(style: &Style) -> String {
+        let ([r, g, b], alpha) = color(style);
+        format!(
+            r#"stroke="rgb({},{},{})" stroke-opacity="{}" stroke-width="{}""#,
+            r,
+            g,
+            b,
+            number(alpha),
+            number(style.width)
+        )
+    }
+
+    fn escape_xml(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    // a single page as big as the capture, one pdf unit per pixel
+    pub fn pdf(project: &Project) -> Result<Vec<u8>, AppError> {
+        let image = image::load_from_memory(&project.image)
+            .map_err(|e| AppError::Image(e.to_string()))?
+            .to_rgba8();
+        let (width, height) = image.dimensions();
+        let h = height as f32;
+        let rgb: Vec<u8> = image.pixels().flat_map(|p| [p[0], p[1], p[2]]).collect();
+        let alpha: Vec<u8> = image.pixels().map(|p| p[3]).collect();
+        let transparent = alpha.iter().any(|&a| a < 255);
+
+        let mut content = String::new();
+        let _ = writeln!(content, "q {} 0 0 {} 0 0 cm /Im0 Do Q", width, height);
+        let _ = writeln!(content, "1 J 1 j");
+        let mut opacities: Vec<u8> = Vec::new();
+        let mut set_style = |content: &mut String, style: &Style, fill: bool| {
+            let ([r, g, b], _) = color(style);
+            let a = style.color[3];
+            if !opacities.contains(&a) {
+                opacities.push(a);
+            }
+            let _ = writeln!(
+                content,
+                "/GS{} gs {} {} {} {}",
+                a,
+                number(r as f32 / 255.0),
+                number(g as f32 / 255.0),
+                number(b as f32 / 255.0),
+                if fill { "rg" } else { "RG" }
+            );
+        };
+        for element in elements(&project.document.annotations) {
+            match element {
+                Element::Polyline(points, style, closed) => {
+                    set_style(&mut content, &style, false);
+                    let _ = write!(content, "{} w", number(style.width));
+                    for (i, p) in points.iter().enumerate() {
+                        let op = if i == 0 { "m" } else { "l" };
+                        let _ = write!(content, " {} {} {}", number(p.x), number(h - p.y), op);
+                    }
+                    let _ = writeln!(content, " {}", if closed { "s" } else { "S" });
+                }
+                Element::Circle(center, radius, style) => {
+                    set_style(&mut content, &style, false);
+                    let _ = writeln!(
+                        content,
+                        "{} w {} s",
+                        number(style.width),
+                        pdf_circle(center, radius, h)
+                    );
+                }
+                Element::Inset(source, inset, circle) => {
+                    let ((sx, sy), (tx, ty)) = inset_transform(source, inset);
+                    let clip = if circle {
+                        pdf_circle(center(inset), inset.width / 2.0, h)
+                    } else {
+                        format!(
+                            "{} {} {} {} re",
+                            number(inset.x),
+                            number(h - inset.y - inset.height),
+                            number(inset.width),
+                            number(inset.height)
+                        )
+                    };
+                    // the same mapping with y going up, then the capture as on the page
+                    let _ = writeln!(
+                        content,
+                        "q {} W n {} 0 0 {} {} {} cm {} 0 0 {} 0 0 cm /Im0 Do Q",
+                        clip,
+                        number(sx),
+                        number(sy),
+                        number(tx),
+                        number(h - sy * h - ty),
+                        width,
+                        height
+                    );
+                }
+                Element::Text(position, text, style) => {
+                    set_style(&mut content, &style, true);
+                    let size = font_size(&style);
+                    for (i, line) in text.lines().enumerate() {
+                        let baseline = position.y + size * 0.8 + i as f32 * size * 1.2;
+                        let _ = writeln!(
+                            content,
+                            "BT /F0 {} Tf {} {} Td ({}) Tj ET",
+                            number(size),
+                            number(position.x),
+                            number(h - baseline),
+                            escape_pdf(line)
+                        );
+                    }
+                }
+            }
+        }
+        // one graphics state for each opacity, named after the alpha value
+        let states: Vec<String> = opacities
+            .iter()
+            .map(|a| {
+                let opacity = number(*a as f32 / 255.0);
+                format!("/GS{} << /CA {} /ca {} >>", a, opacity, opacity)
+            })
+            .collect();
+        let states = states.join(" ");
+
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        objects.push(b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec());
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /XObject << /Im0 4 0 R >> /Font << /F0 5 0 R >> /ExtGState << {} >> >> /Contents 6 0 R >>",
+                width, height, states
+            )
+            .into_bytes(),
+        );
+        let smask = if transparent { " /SMask 7 0 R" } else { "" };
+        objects.push(stream(
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8{}",
+                width, height, smask
+            ),
+            &rgb,
+        )?);
+        objects.push(
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+        );
+        objects.push(stream("", content.as_bytes())?);
+        if transparent {
+            objects.push(stream(
+                &format!(
+                    "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray /BitsPerComponent 8",
+                    width, height
+                ),
+                &alpha,
+            )?);
+        }
+
+        let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = pdf.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(trailer, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            trailer,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        );
+        pdf.extend_from_slice(trailer.as_bytes());
+        Ok(pdf)
+    }
+
+    // four bezier curves, y going up
+    fn pdf_circle(center: Point, radius: f32, h: f32) -> String {
+        let k = radius * 0.552_284_8;
+        let (x, y) = (center.x, h - center.y);
+        format!(
+            "{} {} m {} {} {} {} {} {} c {} {} {} {} {} {} c {} {} {} {} {} {} c {} {} {} {} {} {} c",
+            number(x + radius), number(y),
+            number(x + radius), number(y + k), number(x + k), number(y + radius), number(x), number(y + radius),
+            number(x - k), number(y + radius), number(x - radius), number(y + k), number(x - radius), number(y),
+            number(x - radius), number(y - k), number(x - k), number(y - radius), number(x), number(y - radius),
+            number(x + k), number(y - radius), number(x + radius), number(y - k), number(x + radius), number(y)
+        )
+    }
+
+    fn stream(dictionary: &str, data: &[u8]) -> Result<Vec<u8>, AppError> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(data)
+            .map_err(|e| AppError::Image(e.to_string()))?;
+        let data = encoder
+            .finish()
+            .map_err(|e| AppError::Image(e.to_string()))?;
+        let mut object = format!(
+            "<< {} /Filter /FlateDecode /Length {} >>\nstream\n",
+            dictionary,
+            data.len()
+        )
+        .into_bytes();
+        object.extend_from_slice(&data);
+        object.extend_from_slice(b"\nendstream");
+        Ok(object)
+    }
+
+    // draws the annotations on the capture, so the result looks like the editor
+    pub fn flatten(project: &Project) -> Result<Vec<u8>, AppError> {
+        let mut image = image::load_from_memory(&project.image)
+            .map_err(|e| AppError::Image(e.to_string()))?
+            .to_rgba8();
+        // the fonts of egui, so the text has the same shape as on the screen
+        let definitions = egui::FontDefinitions::default();
+        let fonts: Vec<FontRef> = definitions
+            .families
+            .get(&egui::FontFamily::Proportional)
+            .into_iter()
+            .flatten()
+            .filter_map(|name| definitions.font_data.get(name))
+            .filter_map(|data| FontRef::try_from_slice_and_index(&data.font, data.index).ok())
+            .collect();
+        // the magnifiers sample the capture, not what is drawn on it
+        let capture = image.clone();
+        for element in elements(&project.document.annotations) {
+            match element {
+                Element::Inset(source, inset, circle) => {
+                    let (x, y) = (source.x.max(0.0).round(), source.y.max(0.0).round());
+                    let width = (source.x + source.width).min(capture.width() as f32).round() - x;
+                    let height = (source.y + source.height).min(capture.height() as f32).round() - y;
+                    let (inset_width, inset_height) = (inset.width.round(), inset.height.round());
+                    if width < 1.0 || height < 1.0 || inset_width < 1.0 || inset_height < 1.0 {
+                        continue;
+                    }
+                    let part = imageops::crop_imm(
+                        &capture,
+                        x as u32,
+                        y as u32,
+                        width as u32,
+                        height as u32,
+                    )
+                    .to_image();
+                    let mut part = imageops::resize(
+                        &part,
+                        inset_width as u32,
+                        inset_height as u32,
+                        FilterType::CatmullRom,
+                    );
+                    if circle {
+                        let radius = inset_width / 2.0;
+                        for (px, py, pixel) in part.enumerate_pixels_mut() {
+                            let d = (px as f32 + 0.5 - radius).hypot(py as f32 + 0.5 - radius);
+                            let coverage = (radius - d + 0.5).clamp(0.0, 1.0);
+                            pixel[3] = (pixel[3] as f32 * coverage).round() as u8;
+                        }
+                    }
+                    imageops::overlay(
+                        &mut image,
+                        &part,
+                        inset.x.round() as i64,
+                        inset.y.round() as i64,
+                    );
+                }
+                Element::Polyline(points, style, closed) => {
+                    let mut segments: Vec<(Point, Point)> =
+                        points.windows(2).map(|w| (w[0], w[1])).collect();
+                    if closed && points.len() > 2 {
+                        segments.push((points[points.len() - 1], points[0]));
+                    }
+                    let half = style.width / 2.0 + 1.0;
+                    let (min, max) = bounds(&points);
+                    let mut mask = Mask::new(
+                        &image,
+                        Point {
+                            x: min.x - half,
+                            y: min.y - half,
+                        },
+                        Point {
+                            x: max.x + half,
+                            y: max.y + half,
+                        },
+                    );
+                    for (a, b) in segments {
+                        let min = Point {
+                            x: a.x.min(b.x),
+                            y: a.y.min(b.y),
+                        };
+                        let max = Point {
+                            x: a.x.max(b.x),
+                            y: a.y.max(b.y),
+                        };
+                        mask.stroke(min, max, style.width, |x, y| segment_distance(x, y, a, b));
+                    }
+                    mask.blend(&mut image, style.color);
+                }
+                Element::Circle(center, radius, style) => {
+                    let min = Point {
+                        x: center.x - radius,
+                        y: center.y - radius,
+                    };
+                    let max = Point {
+                        x: center.x + radius,
+                        y: center.y + radius,
+                    };
+                    let half = style.width / 2.0 + 1.0;
+                    let mut mask = Mask::new(
+                        &image,
+                        Point {
+                            x: min.x - half,
+                            y: min.y - half,
+                        },
+                        Point {
+                            x: max.x + half,
+                            y: max.y + half,
+                        },
+                    );
+                    mask.stroke(min, max, style.width, |x, y| {
+                        ((x - center.x).hypot(y - center.y) - radius).abs()
+                    });
+                    mask.blend(&mut image, style.color);
+                }
+                Element::Text(position, text, style) => {
+                    let first = match fonts.first() {
+                        Some(font) => font.as_scaled(font_size(&style)),
+                        None => continue,
+                    };
+                    let line_height = first.ascent() - first.descent() + first.line_gap();
+                    // the glyphs first, the mask only needs to cover them
+                    let mut outlines = Vec::new();
+                    for (i, line) in text.lines().enumerate() {
+                        let baseline = position.y + first.ascent() + i as f32 * line_height;
+                        let mut x = position.x;
+                        for c in line.chars() {
+                            // the first font that knows the character, like egui does
+                            let font = fonts
+                                .iter()
+                                .find(|f| f.glyph_id(c).0 != 0)
+                                .unwrap_or(&fonts[0])
+                                .as_scaled(font_size(&style));
+                            let id = font.glyph_id(c);
+                            let glyph = id.with_scale_and_position(
+                                font_size(&style),
+                                ab_glyph::point(x, baseline),
+                            );
+                            if let Some(outline) = font.outline_glyph(glyph) {
+                                outlines.push(outline);
+                            }
+                            x += font.h_advance(id);
+                        }
+                    }
+                    let corners: Vec<Point> = outlines
+                        .iter()
+                        .flat_map(|o| {
+                            let b = o.px_bounds();
+                            [Point { x: b.min.x, y: b.min.y }, Point { x: b.max.x, y: b.max.y }]
+                        })
+                        .collect();
+                    let (min, max) = bounds(&corners);
+                    let mut mask = Mask::new(&image, min, max);
+                    for outline in outlines {
+                        let bounds = outline.px_bounds();
+                        outline.draw(|gx, gy, coverage| {
+                            mask.set(
+                                bounds.min.x as i64 + gx as i64,
+                                bounds.min.y as i64 + gy as i64,
+                                coverage,
+                            )
+                        });
+                    }
+                    mask.blend(&mut image, style.color);
+                }
+            }
+        }
+        let mut buffer = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(
+                &mut std::io::Cursor::new(&mut buffer),
+                image::ImageOutputFormat::Png,
+            )
+            .map_err(|e| AppError::Image(e.to_string()))?;
+        Ok(buffer)
+    }
+
+    fn segment_distance(x: f32, y: f32, a: Point, b: Point) -> f32 {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let length = dx * dx + dy * dy;
+        let t = if length > 0.0 {
+            (((x - a.x) * dx + (y - a.y) * dy) / length).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (x - (a.x + t * dx)).hypot(y - (a.y + t * dy))
+    }
+
+    // the smallest and the largest coordinates of the points
+    fn bounds(points: &[Point]) -> (Point, Point) {
+        let mut min = Point {
+            x: f32::MAX,
+            y: f32::MAX,
+        };
+        let mut max = Point {
+            x: f32::MIN,
+            y: f32::MIN,
+        };
+        for p in points {
+            min = Point {
+                x: min.x.min(p.x),
+                y: min.y.min(p.y),
+            };
+            max = Point {
+                x: max.x.max(p.x),
+                y: max.y.max(p.y),
+            };
+        }
+        (min, max)
+    }
+
+    // coverage of one shape, blended once so overlapping segments don't get darker;
+    // it only holds the box the shape can reach, in pixels of the image
+    struct Mask {
+        left: u32,
+        top: u32,
+        width: u32,
+        height: u32,
+        coverage: Vec<f32>,
+        touched: Option<(u32, u32, u32, u32)>, // the pixels to blend, min x, min y, max x, max y
+    }
+
+    impl Mask {
+        fn new(image: &image::RgbaImage, min: Point, max: Point) -> Self {
+            let clamp = |v: f32, limit: u32| v.clamp(0.0, limit as f32) as u32;
+            let (left, top) = (
+                clamp(min.x.floor(), image.width()),
+                clamp(min.y.floor(), image.height()),
+            );
+            let (right, bottom) = (
+                clamp(max.x.ceil() + 1.0, image.width()),
+                clamp(max.y.ceil() + 1.0, image.height()),
+            );
+            let (width, height) = (right.saturating_sub(left), bottom.saturating_sub(top));
+            Mask {
+                left,
+                top,
+                width,
+                height,
+                coverage: vec![0.0; (width * height) as usize],
+                touched: None,
+            }
+        }
+
+        fn index(&self, x: u32, y: u32) -> usize {
+            ((y - self.top) * self.width + x - self.left) as usize
+        }
+
+        fn set(&mut self, x: i64, y: i64, coverage: f32) {
+            let (left, top) = (self.left as i64, self.top as i64);
+            if x >= left
+                && y >= top
+                && x < left + self.width as i64
+                && y < top + self.height as i64
+            {
+                let (x, y) = (x as u32, y as u32);
+                let i = self.index(x, y);
+                self.coverage[i] = self.coverage[i].max(coverage.clamp(0.0, 1.0));
+                self.touched = Some(match self.touched {
+                    Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                    None => (x, y, x, y),
+                });
+            }
+        }
+
+        // distance is how far the center of a pixel is from the middle of the stroke
+        fn stroke(
+            &mut self,
+            min: Point,
+            max: Point,
+            width: f32,
+            distance: impl Fn(f32, f32) -> f32,
+        ) {
+            let half = width / 2.0 + 1.0;
+            let (x0, y0) = ((min.x - half).floor() as i64, (min.y - half).floor() as i64);
+            let (x1, y1) = ((max.x + half).ceil() as i64, (max.y + half).ceil() as i64);
+            let (left, top) = (self.left as i64, self.top as i64);
+            for y in y0.max(top)..=y1.min(top + self.height as i64 - 1) {
+                for x in x0.max(left)..=x1.min(left + self.width as i64 - 1) {
+                    let d = distance(x as f32 + 0.5, y as f32 + 0.5);
+                    self.set(x, y, width / 2.0 - d + 0.5);
+                }
+            }
+        }
+
+        // color is premultiplied, like egui
+        fn blend(&self, image: &mut image::RgbaImage, color: [u8; 4]) {
+            let (x0, y0, x1, y1) = match self.touched {
+                Some(touched) => touched,
+                None => return,
+            };
+            for (x, y) in (y0..=y1).flat_map(|y| (x0..=x1).map(move |x| (x, y))) {
+                let coverage = self.coverage[self.index(x, y)];
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let pixel = image.get_pixel_mut(x, y);
+                let source = color.map(|c| c as f32 / 255.0 * coverage);
+                let alpha = pixel[3] as f32 / 255.0;
+                let out_alpha = source[3] + alpha * (1.0 - source[3]);
+                if out_alpha <= 0.0 {
+                    continue;
+                }
+                for c in 0..3 {
+                    let destination = pixel[c] as f32 / 255.0 * alpha;
+                    let value = (source[c] + destination * (1.0 - source[3])) / out_alpha;
+                    pixel[c] = (value * 255.0).round().clamp(0.0, 255.0) as u8;
+                }
+                pixel[3] = (out_alpha * 255.0).round() as u8;
+            }
+        }
+    }
+
+    // the standard fonts only know latin-1, other characters become ?
+    fn escape_pdf(text: &str) -> String {
+        let mut escaped = String::new();
+        for c in text.chars() {
+            match c {
+                '(' | ')' | '\\' => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                ' '..='~' => escaped.push(c),
+                '\u{a0}'..='\u{ff}' => {
+                    let _ = write!(escaped, "\\{:03o}", c as u32);
+                }
+                _ => escaped.push('?'),
+            }
+        }
+        escaped
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::project::project::{Document, Magnifier, Metadata, Shape, Text};
+        use flate2::read::ZlibDecoder;
+        use std::io::Read;
+
+        fn png(image: image::RgbaImage) -> Vec<u8> {
+            let mut buffer = Vec::new();
+            image::DynamicImage::ImageRgba8(image)
+                .write_to(
+                    &mut std::io::Cursor::new(&mut buffer),
+                    image::ImageOutputFormat::Png,
+                )
+                .unwrap();
+            buffer
+        }
+
+        fn style(width: f32) -> Style {
+            Style {
+                width,
+                color: [200, 0, 0, 200],
+            }
+        }
+
+        fn shape(points: &[(f32, f32)], width: f32) -> Shape {
+            Shape {
+                points: points.iter().map(|&(x, y)| Point { x, y }).collect(),
+                style: style(width),
+            }
+        }
+
+        // every kind of annotation on a capture with a transparent pixel
+        fn project() -> Project {
+            let mut image = image::RgbaImage::from_pixel(8, 6, image::Rgba([10, 20, 30, 255]));
+            image.put_pixel(7, 5, image::Rgba([0, 0, 0, 0]));
+            let annotations = Annotations {
+                pen: vec![shape(&[(0.0, 0.0), (2.0, 1.0), (3.0, 3.0)], 1.0)],
+                line: vec![shape(&[(0.0, 5.0), (7.0, 0.0)], 2.0)],
+                arrow: vec![shape(&[(1.0, 1.0), (6.0, 1.0)], 1.0)],
+                rect: vec![shape(&[(1.0, 1.0), (5.0, 4.0)], 1.0)],
+                circle: vec![shape(&[(4.0, 3.0), (6.0, 3.0)], 1.0)],
+                text: vec![Text {
+                    position: Point { x: 0.5, y: 0.5 },
+                    text: "a <b> & (c)\nsecond é ✓".to_string(),
+                    style: style(1.0),
+                }],
+                magnifier: vec![Magnifier {
+                    source: Rect {
+                        x: 0.0,
+                        y: 0.0,
+                        width: 2.0,
+                        height: 2.0,
+                    },
+                    inset: Rect {
+                        x: 4.0,
+                        y: 2.0,
+                        width: 4.0,
+                        height: 4.0,
+                    },
+                    circle: true,
+                    style: style(0.5),
+                }],
+            };
+            Project {
+                image: png(image),
+                document: Document::new(Metadata::default(), annotations, None),
+            }
+        }
+
+        fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+            haystack[from..]
+                .windows(needle.len())
+                .position(|w| w == needle)
+                .map(|i| i + from)
+        }
+
+        // the data of object n, inflated
+        fn stream_of(pdf: &[u8], n: usize) -> Vec<u8> {
+            let start = find(pdf, format!("\n{} 0 obj\n", n).as_bytes(), 0).unwrap();
+            let length_at = find(pdf, b"/Length ", start).unwrap() + 8;
+            let length_end = find(pdf, b" >>", length_at).unwrap();
+            let length: usize = std::str::from_utf8(&pdf[length_at..length_end])
+                .unwrap()
+                .parse()
+                .unwrap();
+            let data = find(pdf, b"stream\n", length_end).unwrap() + 7;
+            assert_eq!(&pdf[data + length..data + length + 10], b"\nendstream");
+            let mut inflated = Vec::new();
+            ZlibDecoder::new(&pdf[data..data + length])
+                .read_to_end(&mut inflated)
+                .unwrap();
+            inflated
+        }
+
+        #[test]
+        fn pdf_xref_points_to_the_objects() {
+            let pdf = pdf(&project()).unwrap();
+            // the image data is binary, the table and the trailer are not
+            let startxref = pdf.windows(10).rposition(|w| w == b"startxref\n").unwrap();
+            let trailer = std::str::from_utf8(&pdf[startxref + 10..]).unwrap();
+            let xref: usize = trailer.lines().next().unwrap().parse().unwrap();
+            let text = std::str::from_utf8(&pdf[xref..]).unwrap();
+            let mut lines = text.lines();
+            assert_eq!(lines.next(), Some("xref"));
+            let count: usize = lines.next().unwrap()[2..].parse().unwrap();
+            // the image has a transparent pixel, so the soft mask is the 7th object
+            assert_eq!(count, 8);
+            assert_eq!(lines.next(), Some("0000000000 65535 f "));
+            for n in 1..count {
+                let entry = lines.next().unwrap();
+                assert_eq!(entry.len(), 19);
+                assert!(entry.ends_with(" 00000 n "));
+                let offset: usize = entry[..10].parse().unwrap();
+                assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", n).as_bytes()));
+            }
+            assert_eq!(lines.next(), Some("trailer"));
+            assert!(text.contains(&format!("/Size {} /Root 1 0 R", count)));
+            assert!(text.ends_with("%%EOF\n"));
+        }
+
+        #[test]
+        fn pdf_keeps_the_pixels() {
+            let project = project();
+            let pdf = pdf(&project).unwrap();
+            let image = image::load_from_memory(&project.image).unwrap().to_rgba8();
+            let rgb: Vec<u8> = image.pixels().flat_map(|p| [p[0], p[1], p[2]]).collect();
+            let alpha: Vec<u8> = image.pixels().map(|p| p[3]).collect();
+            assert_eq!(stream_of(&pdf, 4), rgb);
+            assert_eq!(stream_of(&pdf, 7), alpha);
+            let content = String::from_utf8(stream_of(&pdf, 6)).unwrap();
+            assert!(content.starts_with("q 8 0 0 6 0 0 cm /Im0 Do Q\n"));
+            assert!(content.contains("(a <b> & \\(c\\)) Tj"));
+            assert!(content.contains("(second \\351 ?) Tj"));
+        }
+
+        #[test]
+        fn pdf_without_transparency_has_no_mask() {
+            let mut project = project();
+            project.image = png(image::RgbaImage::from_pixel(3, 2, image::Rgba([1, 2, 3, 255])));
+            let pdf = String::from_utf8_lossy(&pdf(&project).unwrap()).to_string();
+            assert!(!pdf.contains("/SMask"));
+            assert!(pdf.contains("/Size 7 /Root 1 0 R"));
+        }
+
+        #[test]
+        fn svg_embeds_the_capture() {
+            let project = project();
+            let svg = svg(&project).unwrap();
+            assert!(svg.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<svg "));
+            assert!(svg.ends_with("</svg>\n"));
+            assert!(svg.contains(r#"width="8" height="6" viewBox="0 0 8 6""#));
+            let href = svg.split("data:image/png;base64,").nth(1).unwrap();
+            let data = &href[..href.find('"').unwrap()];
+            let decoded = base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .unwrap();
+            assert_eq!(decoded, project.image);
+        }
+
+        #[test]
+        fn svg_has_every_annotation() {
+            let svg = svg(&project()).unwrap();
+            // the magnifier link, the arrow and its two head lines, the pen and the line
+            assert_eq!(svg.matches("<polyline ").count(), 6);
+            assert_eq!(svg.matches("<polygon ").count(), 1);
+            // the circle, both outlines of the magnifier and the clip of its inset
+            assert_eq!(svg.matches("<circle ").count(), 4);
+            assert_eq!(svg.matches("<clipPath ").count(), 1);
+            assert_eq!(svg.matches("<use ").count(), 1);
+            assert_eq!(svg.matches("<text ").count(), 1);
+            assert_eq!(svg.matches("<tspan ").count(), 2);
+            assert!(svg.contains(">a &lt;b&gt; &amp; (c)</tspan>"));
+            assert!(svg.contains(">second é ✓</tspan>"));
+        }
+
+        fn flat(project: &Project) -> image::RgbaImage {
+            image::load_from_memory(&flatten(project).unwrap())
+                .unwrap()
+                .to_rgba8()
+        }
+
+        // an opaque red shape on a white capture, nothing else
+        fn single(annotations: Annotations) -> Project {
+            Project {
+                image: png(image::RgbaImage::from_pixel(
+                    40,
+                    30,
+                    image::Rgba([255, 255, 255, 255]),
+                )),
+                document: Document::new(Metadata::default(), annotations, None),
+            }
+        }
+
+        fn red(points: &[(f32, f32)], width: f32) -> Shape {
+            Shape {
+                points: points.iter().map(|&(x, y)| Point { x, y }).collect(),
+                style: Style {
+                    width,
+                    color: [255, 0, 0, 255],
+                },
+            }
+        }
+
+        const RED: image::Rgba<u8> = image::Rgba([255, 0, 0, 255]);
+        const WHITE: image::Rgba<u8> = image::Rgba([255, 255, 255, 255]);
+
+        #[test]
+        fn flatten_without_annotations_keeps_the_pixels() {
+            let project = single(Annotations::default());
+            let original = image::load_from_memory(&project.image).unwrap().to_rgba8();
+            assert_eq!(flat(&project), original);
+        }
+
+        #[test]
+        fn flatten_draws_a_line_only_where_it_is() {
+            let image = flat(&single(Annotations {
+                line: vec![red(&[(5.0, 10.5), (35.0, 10.5)], 3.0)],
+                ..Default::default()
+            }));
+            assert_eq!(image.dimensions(), (40, 30));
+            assert_eq!(*image.get_pixel(20, 10), RED);
+            assert_eq!(*image.get_pixel(20, 20), WHITE);
+            assert_eq!(*image.get_pixel(0, 10), WHITE);
+            assert_eq!(*image.get_pixel(39, 10), WHITE);
+        }
+
+        #[test]
+        fn flatten_blends_overlapping_segments_once() {
+            let color = [100, 0, 0, 128];
+            let pen = |points: &[(f32, f32)]| Shape {
+                points: points.iter().map(|&(x, y)| Point { x, y }).collect(),
+                style: Style { width: 4.0, color },
+            };
+            let once = flat(&single(Annotations {
+                pen: vec![pen(&[(5.0, 15.0), (35.0, 15.0)])],
+                ..Default::default()
+            }));
+            let back_and_forth = flat(&single(Annotations {
+                pen: vec![pen(&[(5.0, 15.0), (35.0, 15.0), (5.0, 15.0)])],
+                ..Default::default()
+            }));
+            assert_ne!(*once.get_pixel(20, 15), WHITE);
+            assert_eq!(once, back_and_forth);
+        }
+
+        #[test]
+        fn flatten_clips_shapes_outside_the_capture() {
+            let image = flat(&single(Annotations {
+                rect: vec![red(&[(-10.0, -10.0), (20.5, 15.5)], 3.0)],
+                circle: vec![red(&[(40.0, 30.0), (60.0, 30.0)], 2.0)],
+                line: vec![red(&[(-100.0, 25.5), (100.0, 25.5)], 1.0)],
+                ..Default::default()
+            }));
+            // the right and bottom sides of the rectangle, the arc in the corner, the whole line
+            assert_eq!(*image.get_pixel(20, 5), RED);
+            assert_eq!(*image.get_pixel(10, 15), RED);
+            assert_eq!(*image.get_pixel(10, 5), WHITE);
+            assert_eq!(*image.get_pixel(39, 10), RED);
+            assert_eq!(*image.get_pixel(0, 25), RED);
+            assert_eq!(*image.get_pixel(39, 25), RED);
+        }
+
+        #[test]
+        fn flatten_draws_the_text_from_its_position() {
+            let image = flat(&single(Annotations {
+                text: vec![Text {
+                    position: Point { x: 20.0, y: 10.0 },
+                    text: "MW".to_string(),
+                    style: Style {
+                        width: 2.0,
+                        color: [255, 0, 0, 255],
+                    },
+                }],
+                ..Default::default()
+            }));
+            let drawn: Vec<(u32, u32)> = image
+                .enumerate_pixels()
+                .filter(|(_, _, p)| **p != WHITE)
+                .map(|(x, y, _)| (x, y))
+                .collect();
+            assert!(!drawn.is_empty());
+            assert!(drawn.iter().all(|&(x, y)| x >= 20 && y >= 10));
+        }
+
+        #[test]
+        fn flatten_magnifier_shows_the_source() {
+            let mut capture = image::RgbaImage::from_pixel(40, 30, WHITE);
+            for (x, y, pixel) in capture.enumerate_pixels_mut() {
+                if x < 4 && y < 4 {
+                    *pixel = image::Rgba([0, 0, 255, 255]);
+                }
+            }
+            let mut project = single(Annotations {
+                magnifier: vec![Magnifier {
+                    source: Rect {
+                        x: 0.0,
+                        y: 0.0,
+                        width: 4.0,
+                        height: 4.0,
+                    },
+                    inset: Rect {
+                        x: 20.0,
+                        y: 10.0,
+                        width: 16.0,
+                        height: 16.0,
+                    },
+                    circle: false,
+                    style: Style {
+                        width: 1.0,
+                        color: [255, 0, 0, 255],
+                    },
+                }],
+                ..Default::default()
+            });
+            project.image = png(capture);
+            let image = flat(&project);
+            assert_eq!(*image.get_pixel(28, 18), image::Rgba([0, 0, 255, 255]));
+            assert_eq!(*image.get_pixel(10, 25), WHITE);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+pub mod watch {
+    use crate::schermi::schermi::capture_rgba;
+    use crate::RectangleCrop;
+    use eframe::egui;
+    use image::RgbaImage;
+    use screenshots::Screen;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    const PIXEL_TOLERANCE: i32 = 24; // ignores small changes like antialiasing and compression noise
+
+    pub struct Watcher {
+        stop: Arc<AtomicBool>,
+        triggered: Arc<AtomicU32>,
+        triggers: u32,
+    }
+
+    impl Watcher {
+        // threshold: percentage of changed pixels that triggers a capture;
+        // ignore: the control window of the app on the desktop, its changes don't count
+        pub fn start(
+            ctx: &egui::Context,
+            screen: Screen,
+            rect: Option<RectangleCrop>,
+            ignore: egui::Rect,
+            polls_per_second: u32,
+            threshold: f32,
+            cooldown: Duration,
+        ) -> Self {
+            let stop = Arc::new(AtomicBool::new(false));
+            let triggered = Arc::new(AtomicU32::new(0));
+            let stop_thread = stop.clone();
+            let triggered_thread = triggered.clone();
+            let ctx = ctx.clone();
+            let interval = Duration::from_secs_f64(1.0 / polls_per_second.max(1) as f64);
+            std::thread::spawn(move || {
+                let mut reference = capture_rgba(&screen, rect.as_ref());
+                let ignored = reference
+                    .as_ref()
+                    .and_then(|r| covered(&screen, rect.as_ref(), ignore, r.dimensions()));
+                let mut last_trigger: Option<Instant> = None;
+                while !stop_thread.load(Ordering::Relaxed) {
+                    std::thread::sleep(interval);
+                    let current = match capture_rgba(&screen, rect.as_ref()) {
+                        Some(current) => current,
+                        None => continue,
+                    };
+                    let changed = match &reference {
+                        Some(reference) => difference(reference, &current, ignored) > threshold,
+                        None => false,
+                    };
+                    let cooled = last_trigger.is_none_or(|t| t.elapsed() >= cooldown);
+                    if changed && cooled {
+                        triggered_thread.fetch_add(1, Ordering::SeqCst);
+                        ctx.request_repaint();
+                        last_trigger = Some(Instant::now());
+                    }
+                    if changed || reference.is_none() {
+                        reference = Some(current);
+                    }
+                }
+            });
+            Watcher {
+                stop,
+                triggered,
+                triggers: 0,
+            }
+        }
+
+        pub fn take_triggered(&mut self) -> bool {
+            if self.triggered.swap(0, Ordering::SeqCst) > 0 {
+                self.triggers += 1;
+                true
+            } else {
+                false
+            }
+        }
+
+        pub fn triggers(&self) -> u32 {
+            self.triggers
+        }
+
+        pub fn stop(&self) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    impl Drop for Watcher {
+        fn drop(&mut self) {
+            self.stop();
+        }
+    }
+
+    // the pixels of the frames under a rectangle of the desktop, as min x, min y, max x, max y
+    // with the max excluded; the frames can have more pixels than points on hidpi screens
+    fn covered(
+        screen: &Screen,
+        rect: Option<&RectangleCrop>,
+        area: egui::Rect,
+        (width, height): (u32, u32),
+    ) -> Option<(u32, u32, u32, u32)> {
+        let display = screen.display_info;
+        let origin = egui::pos2(display.x as f32, display.y as f32);
+        let frame = match rect {
+            Some(rect) => egui::Rect::from_min_size(
+                origin + egui::vec2(rect.x_left.floor(), rect.y_left.floor()),
+                egui::vec2(rect.width.floor(), rect.height.floor()),
+            ),
+            None => egui::Rect::from_min_size(
+                origin,
+                egui::vec2(display.width as f32, display.height as f32),
+            ),
+        };
+        let scale = egui::vec2(
+            width as f32 / frame.width().max(1.0),
+            height as f32 / frame.height().max(1.0),
+        );
+        let x = |v: f32| ((v - frame.min.x) * scale.x).clamp(0.0, width as f32);
+        let y = |v: f32| ((v - frame.min.y) * scale.y).clamp(0.0, height as f32);
+        let (x0, y0) = (x(area.min.x).floor() as u32, y(area.min.y).floor() as u32);
+        let (x1, y1) = (x(area.max.x).ceil() as u32, y(area.max.y).ceil() as u32);
+        if x0 < x1 && y0 < y1 {
+            Some((x0, y0, x1, y1))
+        } else {
+            None
+        }
+    }
+
+    // percentage of pixels that changed between two frames, out of the ones not ignored
+    pub fn difference(a: &RgbaImage, b: &RgbaImage, ignored: Option<(u32, u32, u32, u32)>) -> f32 {
+        if a.dimensions() != b.dimensions() {
+            return 100.0;
+        }
+        let inside = |x: u32, y: u32| {
+            ignored.is_some_and(|(x0, y0, x1, y1)| x >= x0 && x < x1 && y >= y0 && y < y1)
+        };
+        let changed = a
+            .enumerate_pixels()
+            .zip(b.pixels())
+            .filter(|((x, y, _), _)| !inside(*x, *y))
+            .filter(|((_, _, p), q)| {
+                (0..3).any(|c| (p.0[c] as i32 - q.0[c] as i32).abs() > PIXEL_TOLERANCE)
+            })
+            .count();
+        let ignored = ignored.map_or(0, |(x0, y0, x1, y1)| (x1 - x0) * (y1 - y0));
+        changed as f32 * 100.0 / (a.width() * a.height() - ignored).max(1) as f32
+    }
+}